@@ -6,6 +6,7 @@
 use std::fmt::Write as _;
 #[cfg(windows)]
 use std::os::windows::process::CommandExt as _;
+use std::sync::Arc;
 
 use futures_util::StreamExt;
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -83,7 +84,7 @@ fn silent(cmd: &mut std::process::Command) -> &mut std::process::Command {
 /// Apply the Windows no-window flag to a tokio `Command`. No-op on non-Windows
 /// so the launcher crate compiles for the Linux test runner.
 #[allow(unused_variables, clippy::needless_pass_by_ref_mut)]
-fn no_window(cmd: &mut Command) {
+pub(super) fn no_window(cmd: &mut Command) {
     #[cfg(windows)]
     cmd.creation_flags(CREATE_NO_WINDOW);
 }
@@ -156,7 +157,7 @@ fn validate_model_name(model: &str) -> Result<(), String> {
     Ok(())
 }
 
-fn build_claude_input(messages: &[ChatMessage], screenshot: Option<&str>) -> String {
+fn build_claude_input(messages: &[ChatMessage], screenshots: &[Arc<str>]) -> String {
     // Collect all messages into a single user turn. Claude stream-json expects
     // one user message; conversation history is concatenated as text context.
     let mut combined_text = String::new();
@@ -172,13 +173,13 @@ fn build_claude_input(messages: &[ChatMessage], screenshot: Option<&str>) -> Str
         "text": combined_text,
     })];
 
-    if let Some(data) = screenshot {
+    for data in screenshots {
         content_parts.push(serde_json::json!({
             "type": "image",
             "source": {
                 "type": "base64",
                 "media_type": "image/png",
-                "data": data,
+                "data": &**data,
             }
         }));
     }
@@ -305,7 +306,7 @@ pub async fn stream_claude<F>(
     model: &str,
     system_prompt: &str,
     messages: &[ChatMessage],
-    screenshot: Option<&str>,
+    screenshots: &[Arc<str>],
     on_chunk: F,
 ) -> Result<(), String>
 where
@@ -348,7 +349,7 @@ where
         c
     };
 
-    let input = build_claude_input(messages, screenshot);
+    let input = build_claude_input(messages, screenshots);
     run_cli(&mut cmd, input, on_chunk, parse_claude_line, "Claude").await
 }
 
@@ -568,7 +569,7 @@ mod tests {
 
     #[test]
     fn claude_input_emits_one_ndjson_line_terminated_by_newline() {
-        let out = build_claude_input(&[msg("user", "hello")], None);
+        let out = build_claude_input(&[msg("user", "hello")], &[]);
         assert!(out.ends_with('\n'));
         assert_eq!(out.matches('\n').count(), 1);
     }
@@ -577,7 +578,7 @@ mod tests {
     fn claude_input_concatenates_history_as_single_user_turn() {
         let out = build_claude_input(
             &[msg("user", "q1"), msg("assistant", "a1"), msg("user", "q2")],
-            None,
+            &[],
         );
         let v: serde_json::Value = serde_json::from_str(out.trim_end()).unwrap();
         assert_eq!(v["type"], "user");
@@ -593,7 +594,7 @@ mod tests {
 
     #[test]
     fn claude_input_appends_image_part_when_screenshot_present() {
-        let out = build_claude_input(&[msg("user", "look")], Some("AAAAFAKE=="));
+        let out = build_claude_input(&[msg("user", "look")], &["AAAAFAKE==".into()]);
         let v: serde_json::Value = serde_json::from_str(out.trim_end()).unwrap();
         let parts = v["message"]["content"].as_array().unwrap();
         assert_eq!(parts.len(), 2);
@@ -603,9 +604,20 @@ mod tests {
         assert_eq!(parts[1]["source"]["data"], "AAAAFAKE==");
     }
 
+    #[test]
+    fn claude_input_appends_every_staged_screenshot_in_order() {
+        let shots = ["FIRST==".into(), "SECOND==".into()];
+        let out = build_claude_input(&[msg("user", "compare")], &shots);
+        let v: serde_json::Value = serde_json::from_str(out.trim_end()).unwrap();
+        let parts = v["message"]["content"].as_array().unwrap();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[1]["source"]["data"], "FIRST==");
+        assert_eq!(parts[2]["source"]["data"], "SECOND==");
+    }
+
     #[test]
     fn claude_input_omits_image_when_no_screenshot() {
-        let out = build_claude_input(&[msg("user", "hi")], None);
+        let out = build_claude_input(&[msg("user", "hi")], &[]);
         let v: serde_json::Value = serde_json::from_str(out.trim_end()).unwrap();
         let parts = v["message"]["content"].as_array().unwrap();
         assert_eq!(parts.len(), 1);
@@ -0,0 +1,125 @@
+//! On-demand export of the visible overlay conversation to a markdown file, for
+//! sharing a walkthrough exchange outside the app.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+
+use super::ChatMessage;
+
+/// Names heading each turn (`[overlay] user_name` / `assistant_name`).
+pub struct Speakers<'a> {
+    pub user: &'a str,
+    pub assistant: &'a str,
+}
+
+/// Write `messages` to a timestamped markdown file in `dir` (created if missing)
+/// and return its path.
+pub fn export_conversation(
+    dir: &Path,
+    game: &str,
+    speakers: &Speakers,
+    messages: &[ChatMessage],
+) -> Result<PathBuf, String> {
+    if messages.is_empty() {
+        return Err("Nothing to export yet.".to_owned());
+    }
+    let now = chrono::Local::now();
+    std::fs::create_dir_all(dir).map_err(|e| format!("Failed to create export folder: {e}"))?;
+    let path = dir.join(file_name(game, &now.format("%Y%m%d-%H%M%S").to_string()));
+    let markdown = to_markdown(
+        game,
+        &now.format("%Y-%m-%d %H:%M").to_string(),
+        speakers,
+        messages,
+    );
+    std::fs::write(&path, markdown).map_err(|e| format!("Failed to write export: {e}"))?;
+    Ok(path)
+}
+
+/// `sage-<game>-<stamp>.md`, with the game name reduced to filename-safe
+/// lowercase ASCII.
+fn file_name(game: &str, stamp: &str) -> String {
+    let mut slug = String::new();
+    for character in game.chars() {
+        if character.is_ascii_alphanumeric() {
+            slug.push(character.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-');
+    if slug.is_empty() {
+        format!("sage-{stamp}.md")
+    } else {
+        format!("sage-{slug}-{stamp}.md")
+    }
+}
+
+fn to_markdown(
+    game: &str,
+    exported_at: &str,
+    speakers: &Speakers,
+    messages: &[ChatMessage],
+) -> String {
+    let assistant = speakers.assistant;
+    let mut out = if game.is_empty() {
+        format!("# {assistant} conversation\n\n")
+    } else {
+        format!("# {assistant} conversation -- {game}\n\n")
+    };
+    let _ = writeln!(out, "_Exported {exported_at}_");
+    for message in messages {
+        let speaker = if message.role == "user" {
+            speakers.user
+        } else {
+            assistant
+        };
+        let _ = write!(out, "\n## {speaker}\n\n{}\n", message.content.trim());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{file_name, to_markdown, ChatMessage, Speakers};
+
+    #[test]
+    fn file_name_slugs_the_game_name() {
+        assert_eq!(
+            file_name("ELDEN RING: Nightreign", "20261016-140300"),
+            "sage-elden-ring-nightreign-20261016-140300.md"
+        );
+        assert_eq!(file_name("", "1"), "sage-1.md");
+        assert_eq!(file_name("???", "1"), "sage-1.md");
+    }
+
+    #[test]
+    fn markdown_heads_each_turn() {
+        let messages = [
+            ChatMessage {
+                role: "user".to_owned(),
+                content: "Where next?".to_owned(),
+            },
+            ChatMessage {
+                role: "assistant".to_owned(),
+                content: "Take the lift.\n".to_owned(),
+            },
+        ];
+        let default = Speakers {
+            user: "You",
+            assistant: "Sage",
+        };
+        assert_eq!(
+            to_markdown("Hollow Knight", "2026-10-16 14:03", &default, &messages),
+            "# Sage conversation -- Hollow Knight\n\n_Exported 2026-10-16 14:03_\n\n\
+             ## You\n\nWhere next?\n\n## Sage\n\nTake the lift.\n"
+        );
+        let branded = Speakers {
+            user: "Knight",
+            assistant: "Cornifer",
+        };
+        assert!(to_markdown("", "now", &branded, &messages).starts_with(
+            "# Cornifer conversation\n\n_Exported now_\n\n## Knight\n\nWhere next?\n\n## Cornifer"
+        ));
+    }
+}
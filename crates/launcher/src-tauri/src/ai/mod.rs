@@ -7,9 +7,14 @@
 //! time -- a new request cancels and replaces the previous one.
 
 mod cli;
+mod export;
 mod gemini;
+mod ocr;
+mod translation;
 
 use std::fmt::Write as _;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use base64::Engine as _;
 use parking_lot::Mutex;
@@ -18,13 +23,45 @@ use tauri::ipc::Channel;
 use tauri::{AppHandle, Manager};
 
 use crate::overlay::{GameInfo, OverlayState};
+use crate::state::AppState;
+use translation::TranslationCache;
 
 pub use cli::{detect_cli, ensure_codex_workdir, CliConfig};
+pub use export::{export_conversation, Speakers};
+pub use gemini::{key_source as gemini_key_source, model_name as gemini_model, KeySource};
+pub use translation::{translate_capture, TranslationProvider};
 
 /// Backstop timeout for a single request, covering a hung CLI that never closes
-/// stdout. Gemini has its own (shorter) HTTP timeout, so this is the CLI ceiling.
+/// stdout. Gemini has its own response + stall timeouts, so this is mainly the
+/// CLI ceiling.
 const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_mins(3);
 
+/// Minimum gap between streamed chunk messages to the overlay (~20 per second).
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_millis(50);
+
+/// Rough characters-per-token ratio for the pre-send estimate. Close enough for
+/// English prose on every supported provider; the estimate is only a warning.
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Shown under a reply that only went through after its screenshots were
+/// re-sent at half size.
+const DOWNSCALED_NOTE: &str = "screenshots downscaled to fit";
+
+/// Shown under a Gemini reply that stopped at the output token limit.
+const TRUNCATED_NOTE: &str = "cut off at the token limit -- pick a longer reply length";
+
+/// Bytes read from the end of a `[[games]] context_log`: plenty for the lines
+/// sent, without reading a multi-megabyte log on every question.
+const LOG_TAIL_BYTES: u64 = 64 * 1024;
+
+/// Per-line cost of a `context_log` tail in the estimate, which never reads the
+/// log (it runs on every keystroke).
+const LOG_LINE_TOKENS: usize = 30;
+
+/// Flat per-screenshot cost in the estimate (Gemini bills a tiled 1080p frame at
+/// roughly this many input tokens).
+const IMAGE_TOKENS: usize = 1_300;
+
 /// The provider a request targets. Serialized lowercase to match the overlay UI
 /// (`"gemini"` / `"claude"` / `"openai"`).
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
@@ -46,6 +83,56 @@ impl Provider {
     }
 }
 
+/// The overlay's reply-length preference. Except the default, each appends an
+/// instruction to the system prompt so the CLI providers follow it too; Long
+/// also raises Gemini's output token cap.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyLength {
+    Short,
+    #[default]
+    Medium,
+    Long,
+}
+
+impl ReplyLength {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Short => "short",
+            Self::Medium => "medium",
+            Self::Long => "long",
+        }
+    }
+
+    /// Gemini `maxOutputTokens` for this length. Short is not capped lower:
+    /// 2.5 models spend thinking tokens from the same budget, so a small cap
+    /// ends in `MAX_TOKENS` with little or no reply -- the prompt keeps it short.
+    fn max_output_tokens(self) -> u32 {
+        match self {
+            Self::Short | Self::Medium => gemini::MAX_OUTPUT_TOKENS,
+            Self::Long => 8_192,
+        }
+    }
+
+    /// Instruction appended to the system prompt, if any.
+    fn instruction(self) -> Option<&'static str> {
+        match self {
+            Self::Short => Some("Respond in 1-2 sentences."),
+            Self::Medium => None,
+            Self::Long => Some("Be thorough: explain step by step and mention alternatives."),
+        }
+    }
+
+    /// `system_prompt` with this length's instruction appended.
+    fn apply(self, mut system_prompt: String) -> String {
+        if let Some(instruction) = self.instruction() {
+            system_prompt.push_str("\n\n");
+            system_prompt.push_str(instruction);
+        }
+        system_prompt
+    }
+}
+
 /// One chat turn sent from the overlay UI.
 #[derive(Debug, Clone, Deserialize)]
 pub struct ChatMessage {
@@ -66,6 +153,25 @@ pub struct SageEvent {
     text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     message: Option<String>,
+    /// Web pages cited by search grounding; only set on `"done"`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    sources: Vec<gemini::Source>,
+    /// Response latency; only set on `"done"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timing: Option<Timing>,
+    /// Something the user should know about how the reply was obtained; only
+    /// set on `"done"`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    note: Option<&'static str>,
+}
+
+/// How long a provider took: until the first streamed text, and in total.
+#[derive(Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Timing {
+    /// `None` when the reply carried no text at all.
+    first_chunk_ms: Option<u64>,
+    total_ms: u64,
 }
 
 impl SageEvent {
@@ -76,16 +182,28 @@ impl SageEvent {
             conversation_id,
             text,
             message: None,
+            sources: Vec::new(),
+            timing: None,
+            note: None,
         }
     }
 
-    fn done(request_id: u64, conversation_id: u64) -> Self {
+    fn done(
+        request_id: u64,
+        conversation_id: u64,
+        sources: Vec<gemini::Source>,
+        timing: Timing,
+        note: Option<&'static str>,
+    ) -> Self {
         Self {
             kind: "done",
             request_id,
             conversation_id,
             text: String::new(),
             message: None,
+            sources,
+            timing: Some(timing),
+            note,
         }
     }
 
@@ -96,6 +214,9 @@ impl SageEvent {
             conversation_id,
             text: String::new(),
             message: Some(message),
+            sources: Vec::new(),
+            timing: None,
+            note: None,
         }
     }
 }
@@ -111,6 +232,14 @@ pub struct ProviderAvailability {
     pub openai_where: String,
 }
 
+/// Estimated input size of the next request, for the overlay's budget warning.
+#[derive(Debug, Clone, Serialize)]
+pub struct TokenEstimate {
+    pub tokens: usize,
+    /// Set when `tokens` exceeds the configured `[api] warn_tokens`.
+    pub warn: bool,
+}
+
 /// Parameters of a chat request, deserialized from the `ask_sage` command.
 pub struct RequestParams {
     pub request_id: u64,
@@ -118,6 +247,7 @@ pub struct RequestParams {
     pub provider: Provider,
     pub messages: Vec<ChatMessage>,
     pub attach_screenshot: bool,
+    pub reply_length: ReplyLength,
 }
 
 /// The single in-flight request (if any). Aborting `handle` cancels the request
@@ -127,10 +257,25 @@ struct Active {
     handle: tauri::async_runtime::JoinHandle<()>,
 }
 
-/// Backend AI state: cached CLI availability plus the active-request slot.
+/// Most screenshots that can be staged for one question.
+const MAX_STAGED: usize = 4;
+
+/// Returned for any request made while the kill switch is engaged.
+const PAUSED_MESSAGE: &str = "Sage is paused -- press Ctrl+Shift+X to resume.";
+
+/// Backend AI state: cached CLI availability, the active-request slot, and the
+/// screenshots staged for the next question.
 pub struct AiState {
     cli: Mutex<CliConfig>,
     active: Mutex<Option<Active>>,
+    /// Base64 PNGs captured ahead of sending, held here so they cross the IPC
+    /// boundary once (the overlay only tracks the count). Shared, not copied,
+    /// into each request that sends them.
+    staged: Mutex<Vec<Arc<str>>>,
+    /// Recent translations, so re-translating an unchanged frame is free.
+    translations: Mutex<TranslationCache>,
+    /// Kill switch engaged: no capture or network request is started.
+    paused: AtomicBool,
 }
 
 impl Default for AiState {
@@ -138,6 +283,9 @@ impl Default for AiState {
         Self {
             cli: Mutex::new(CliConfig::default()),
             active: Mutex::new(None),
+            staged: Mutex::new(Vec::new()),
+            translations: Mutex::new(TranslationCache::default()),
+            paused: AtomicBool::new(false),
         }
     }
 }
@@ -149,11 +297,11 @@ impl AiState {
     }
 
     /// Report which providers can currently serve a request. Gemini depends on a
-    /// readable config with a key + model; Claude / Codex on a detected CLI.
+    /// stored or configured key; Claude / Codex on a detected CLI.
     pub fn availability(&self) -> ProviderAvailability {
         let cli = self.cli.lock();
         ProviderAvailability {
-            gemini: gemini::load_config().is_ok(),
+            gemini: gemini::key_source(&crate::config::load()) != KeySource::Missing,
             claude: cli.claude.is_available(),
             openai: cli.codex.is_available(),
             claude_where: cli.claude.location().to_owned(),
@@ -178,6 +326,78 @@ impl AiState {
         }
     }
 
+    /// Whether the kill switch is engaged.
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Flip the kill switch. Engaging it aborts the active request. Returns
+    /// whether Sage is now paused.
+    pub fn toggle_paused(&self) -> bool {
+        let paused = !self.paused.fetch_xor(true, Ordering::SeqCst);
+        if paused {
+            self.cancel_active();
+        }
+        paused
+    }
+
+    /// Abort whatever request is running, whichever it is.
+    pub fn cancel_active(&self) {
+        if let Some(active) = self.active.lock().take() {
+            active.handle.abort();
+        }
+    }
+
+    /// Fail fast while the kill switch is engaged.
+    pub fn ensure_running(&self) -> Result<(), String> {
+        if self.paused.load(Ordering::SeqCst) {
+            Err(PAUSED_MESSAGE.to_owned())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Add a screenshot to the staged list. Returns the new count.
+    pub fn stage(&self, screenshot: Arc<str>) -> Result<usize, String> {
+        let mut staged = self.staged.lock();
+        if staged.len() >= MAX_STAGED {
+            return Err(format!("At most {MAX_STAGED} screenshots can be staged."));
+        }
+        staged.push(screenshot);
+        Ok(staged.len())
+    }
+
+    /// The staged screenshot at `index`, if there is one.
+    pub fn staged_at(&self, index: usize) -> Option<Arc<str>> {
+        self.staged.lock().get(index).cloned()
+    }
+
+    /// Drop the staged screenshot at `index` (if any). Returns the new count.
+    pub fn unstage(&self, index: usize) -> usize {
+        let mut staged = self.staged.lock();
+        if index < staged.len() {
+            staged.remove(index);
+        }
+        staged.len()
+    }
+
+    pub fn clear_staged(&self) {
+        self.staged.lock().clear();
+    }
+
+    /// Forget cached translations (the overlay's New chat).
+    pub fn clear_translations(&self) {
+        self.translations.lock().clear();
+    }
+
+    /// Remove the first `count` staged screenshots once a request has sent them,
+    /// keeping any staged while it streamed.
+    fn drain_staged(&self, count: usize) {
+        let mut staged = self.staged.lock();
+        let count = count.min(staged.len());
+        staged.drain(..count);
+    }
+
     /// Clear the active slot once a request finishes, unless it was already
     /// replaced by a newer request.
     fn clear_if(&self, request_id: u64) {
@@ -189,41 +409,73 @@ impl AiState {
 /// Spawn a chat request, cancelling and replacing any request already running.
 pub fn spawn_request(app: &AppHandle, params: RequestParams, channel: Channel<SageEvent>) {
     let request_id = params.request_id;
+    if let Err(message) = app.state::<AiState>().ensure_running() {
+        let _ = channel.send(SageEvent::error(
+            request_id,
+            params.conversation_id,
+            message,
+        ));
+        return;
+    }
     let handle = tauri::async_runtime::spawn(run(app.clone(), params, channel));
     app.state::<AiState>().replace_active(request_id, handle);
 }
 
-/// Drive one request end to end: build the system prompt + optional screenshot,
+/// Drive one request end to end: build the system prompt + screenshots,
 /// stream the provider through a coalescing buffer, and emit terminal events.
+#[allow(clippy::too_many_lines)] // linear prepare -> stream -> report pipeline
 async fn run(app: AppHandle, params: RequestParams, channel: Channel<SageEvent>) {
     let RequestParams {
         request_id,
         conversation_id,
         provider,
-        messages,
+        mut messages,
         attach_screenshot,
+        reply_length,
     } = params;
+    let config = crate::config::load();
+    trim_history(
+        &mut messages,
+        config.api.max_history_messages,
+        TrimStrategy::from_config(&config.api.trim_strategy),
+    );
 
     // Read shared state up front so no state guard is held across an await.
-    let (system_prompt, game_hwnd) = {
+    let (mut system_prompt, game_hwnd, context_log) = {
         let overlay = app.state::<OverlayState>();
         let game = overlay.game.lock();
         (
-            build_system_prompt(game.as_ref()),
+            build_system_prompt(game.as_ref(), &config),
             game.as_ref().map(|g| g.hwnd),
+            ContextLog::for_game(game.as_ref(), &config),
         )
     };
-    let cli_cfg = app.state::<AiState>().cli.lock().clone();
+    if let Some(log) = context_log {
+        if let Ok(Some(tail)) = tokio::task::spawn_blocking(move || log.tail()).await {
+            append_log_tail(&mut system_prompt, &tail);
+        }
+    }
+    let system_prompt = reply_length.apply(system_prompt);
+    let ai = app.state::<AiState>();
+    let cli_cfg = ai.cli.lock().clone();
+    let gemini_model = app.state::<AppState>().gemini_model();
 
+    // Staged captures go first, in the order taken, then the live frame.
     // Screenshots are skipped for OpenAI (Codex `--image` is broken upstream).
-    let screenshot = if attach_screenshot && provider != Provider::Openai {
-        capture_base64(game_hwnd).await
-    } else {
-        None
-    };
+    let mut screenshots = Vec::new();
+    if provider != Provider::Openai {
+        screenshots.clone_from(&ai.staged.lock());
+    }
+    let staged_count = screenshots.len();
+    if attach_screenshot && provider != Provider::Openai {
+        screenshots.extend(capture_base64(game_hwnd).await);
+    }
 
     let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
     let chan_stream = channel.clone();
+    // Timed from dispatch, after any screenshot capture, so the figures reflect
+    // the provider (network + model) rather than local work.
+    let started = std::time::Instant::now();
 
     let producer = async move {
         let on_chunk = move |text: String| {
@@ -232,145 +484,614 @@ async fn run(app: AppHandle, params: RequestParams, channel: Channel<SageEvent>)
         };
         match provider {
             Provider::Gemini => {
-                let cfg = gemini::load_config()?;
-                gemini::stream(
+                stream_gemini(
                     &messages,
                     &system_prompt,
-                    screenshot,
-                    &cfg.model,
-                    &cfg.api_key,
+                    screenshots,
+                    &gemini_model,
+                    reply_length,
                     on_chunk,
                 )
                 .await
             }
-            Provider::Claude => {
-                cli::stream_claude(
-                    &cli_cfg,
-                    cli::DEFAULT_CLAUDE_MODEL,
-                    &system_prompt,
-                    &messages,
-                    screenshot.as_deref(),
-                    on_chunk,
-                )
+            Provider::Claude => cli::stream_claude(
+                &cli_cfg,
+                cli::DEFAULT_CLAUDE_MODEL,
+                &system_prompt,
+                &messages,
+                &screenshots,
+                on_chunk,
+            )
+            .await
+            .map(|()| (Vec::new(), None)),
+            Provider::Openai => cli::stream_codex(&cli_cfg, &system_prompt, &messages, on_chunk)
                 .await
-            }
-            Provider::Openai => {
-                cli::stream_codex(&cli_cfg, &system_prompt, &messages, on_chunk).await
-            }
+                .map(|()| (Vec::new(), None)),
         }
     };
 
-    // Coalesce bursts: drain everything queued into a single Channel message so a
-    // fast per-token provider (Claude deltas) does not flood the IPC boundary.
+    // Resolves to the time until the first chunk (the first batch is sent as
+    // soon as it arrives, so this is the time to first text).
     let consumer = async move {
-        while let Some(first) = rx.recv().await {
-            let mut batch = first;
-            while let Ok(more) = rx.try_recv() {
-                batch.push_str(&more);
-            }
+        let mut first_chunk = None;
+        coalesce(&mut rx, FLUSH_INTERVAL, |batch| {
+            first_chunk.get_or_insert_with(|| started.elapsed());
             let _ = chan_stream.send(SageEvent::chunk(request_id, conversation_id, batch));
-        }
+        })
+        .await;
+        first_chunk
     };
 
     // Backstop timeout: a hung CLI (no output, never closing stdout) would
     // otherwise leave the join pending forever, stranding the UI on "Streaming".
     // On elapse the futures drop -- killing any CLI child via kill_on_drop.
-    let streamed = async { tokio::join!(producer, consumer).0 };
-    let result = match tokio::time::timeout(REQUEST_TIMEOUT, streamed).await {
-        Ok(result) => result,
-        Err(_) => Err("Request timed out. Try again.".to_owned()),
+    let streamed = async { tokio::join!(producer, consumer) };
+    let (result, first_chunk) = match tokio::time::timeout(REQUEST_TIMEOUT, streamed).await {
+        Ok(joined) => joined,
+        Err(_) => (Err("Request timed out. Try again.".to_owned()), None),
     };
 
     let event = match result {
-        Ok(()) => SageEvent::done(request_id, conversation_id),
+        Ok((sources, note)) => {
+            ai.drain_staged(staged_count);
+            if provider == Provider::Gemini {
+                app.state::<AppState>().record_gemini_request();
+            }
+            let timing = Timing {
+                first_chunk_ms: first_chunk.map(duration_ms),
+                total_ms: duration_ms(started.elapsed()),
+            };
+            tracing::info!(
+                "{} reply in {} ms (first text after {:?} ms)",
+                provider.as_str(),
+                timing.total_ms,
+                timing.first_chunk_ms
+            );
+            crate::overlay::notify_reply_ready(&app, config.overlay.completion_notice());
+            SageEvent::done(request_id, conversation_id, sources, timing, note)
+        }
         Err(message) => SageEvent::error(request_id, conversation_id, message),
     };
     let _ = channel.send(event);
 
-    app.state::<AiState>().clear_if(request_id);
+    ai.clear_if(request_id);
+}
+
+/// Stream a Gemini reply from `model` (empty: the configured one), capped for
+/// `reply_length`. With screenshots attached, a
+/// rejected request is usually an oversized payload: nothing has streamed yet,
+/// so it is retried once with every image at half size, and the reply says so.
+async fn stream_gemini(
+    messages: &[ChatMessage],
+    system_prompt: &str,
+    screenshots: Vec<Arc<str>>,
+    model: &str,
+    reply_length: ReplyLength,
+    mut on_chunk: impl FnMut(String) -> Result<(), String>,
+) -> Result<(Vec<gemini::Source>, Option<&'static str>), String> {
+    let mut cfg = gemini::load_config(model)?;
+    cfg.max_output_tokens = reply_length.max_output_tokens();
+    let first = gemini::stream(
+        messages,
+        system_prompt,
+        screenshots.clone(),
+        &cfg,
+        &mut on_chunk,
+    )
+    .await;
+    let (reply, downscaled) = match first {
+        Err(gemini::GeminiError::Http(400 | 413)) if !screenshots.is_empty() => {
+            tracing::warn!(
+                "Gemini rejected a request with screenshots; retrying them at half size"
+            );
+            let smaller = shrink_screenshots(screenshots).await?;
+            let reply = gemini::stream(messages, system_prompt, smaller, &cfg, on_chunk).await?;
+            (reply, true)
+        }
+        result => (result?, false),
+    };
+    let note = if reply.truncated {
+        Some(TRUNCATED_NOTE)
+    } else if downscaled {
+        Some(DOWNSCALED_NOTE)
+    } else {
+        None
+    };
+    Ok((reply.sources, note))
+}
+
+fn duration_ms(duration: std::time::Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Forward queued chunks to `send` in batches. The first chunk goes out at once
+/// (no added first-token latency); after that, a batch is held open until
+/// `interval` has passed since the previous send, so a fast per-token provider
+/// (Claude deltas land every few milliseconds) produces at most one IPC message
+/// -- and one overlay re-render -- per interval instead of one per delta.
+/// Cancellation is unaffected: aborting the request drops this future at once.
+async fn coalesce(
+    rx: &mut tokio::sync::mpsc::UnboundedReceiver<String>,
+    interval: std::time::Duration,
+    mut send: impl FnMut(String),
+) {
+    let mut last_send: Option<tokio::time::Instant> = None;
+    while let Some(first) = rx.recv().await {
+        let mut batch = first;
+        if let Some(last) = last_send {
+            let deadline = last + interval;
+            while let Ok(Some(more)) = tokio::time::timeout_at(deadline, rx.recv()).await {
+                batch.push_str(&more);
+            }
+        }
+        while let Ok(more) = rx.try_recv() {
+            batch.push_str(&more);
+        }
+        send(batch);
+        last_send = Some(tokio::time::Instant::now());
+    }
+}
+
+/// Estimate the next request's input size (system prompt + history + optional
+/// screenshot) and flag it against the configured `[api] warn_tokens`.
+pub fn estimate_request(
+    app: &AppHandle,
+    messages: &[ChatMessage],
+    attach_screenshot: bool,
+) -> TokenEstimate {
+    let config = crate::config::load();
+    let (system_prompt, log_tokens) = {
+        let overlay = app.state::<OverlayState>();
+        let game = overlay.game.lock();
+        (
+            build_system_prompt(game.as_ref(), &config),
+            ContextLog::for_game(game.as_ref(), &config).map_or(0, |log| log.estimated_tokens()),
+        )
+    };
+    let api = config.api;
+    let mut messages = messages.to_vec();
+    trim_history(
+        &mut messages,
+        api.max_history_messages,
+        TrimStrategy::from_config(&api.trim_strategy),
+    );
+    let images = app.state::<AiState>().staged.lock().len() + usize::from(attach_screenshot);
+    let tokens = estimate_tokens(&system_prompt, &messages, images) + log_tokens;
+    TokenEstimate {
+        tokens,
+        warn: api.warn_tokens > 0 && tokens > api.warn_tokens,
+    }
+}
+
+/// How a history over `[api] max_history_messages` is cut down.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum TrimStrategy {
+    /// Drop the oldest messages.
+    #[default]
+    DropOldest,
+    /// Keep the opening user/assistant exchange (often the one that sets the
+    /// scene) and drop from the middle instead.
+    KeepFirstAndRecent,
+}
+
+impl TrimStrategy {
+    fn from_config(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "drop_oldest" => Self::DropOldest,
+            "keep_first_and_recent" => Self::KeepFirstAndRecent,
+            other => {
+                tracing::warn!(
+                    "config.toml [api] trim_strategy = {other:?} is not drop_oldest/\
+                     keep_first_and_recent; using drop_oldest"
+                );
+                Self::DropOldest
+            }
+        }
+    }
+}
+
+/// Cut the history to at most `max` messages: the most recent ones, plus the
+/// opening user/assistant pair under `KeepFirstAndRecent` (when the history
+/// opens with one and `max` leaves room beside it). The cut moves forward so the
+/// recent slice starts on a user turn (Gemini rejects a conversation that opens
+/// with, or repeats, a model turn). `max == 0` keeps everything.
+fn trim_history(messages: &mut Vec<ChatMessage>, max: usize, strategy: TrimStrategy) {
+    if max == 0 || messages.len() <= max {
+        return;
+    }
+    let opens_with_exchange = messages[0].role == "user" && messages[1].role == "assistant";
+    let pinned = match strategy {
+        TrimStrategy::KeepFirstAndRecent if max > 2 && opens_with_exchange => 2,
+        _ => 0,
+    };
+    let last = messages.len() - 1;
+    let mut start = messages.len() - (max - pinned);
+    while start < last && messages[start].role != "user" {
+        start += 1;
+    }
+    messages.drain(pinned..start);
+}
+
+/// Rough input-token count: text length over `CHARS_PER_TOKEN`, plus a flat
+/// `IMAGE_TOKENS` per attached screenshot.
+fn estimate_tokens(system_prompt: &str, messages: &[ChatMessage], images: usize) -> usize {
+    let chars = system_prompt.chars().count()
+        + messages
+            .iter()
+            .map(|message| message.content.chars().count())
+            .sum::<usize>();
+    chars.div_ceil(CHARS_PER_TOKEN) + images * IMAGE_TOKENS
 }
 
 /// Capture the stored game window and base64-encode it as PNG for an AI request.
 /// Capture failures are non-fatal: the request proceeds without the screenshot.
-async fn capture_base64(game_hwnd: Option<i64>) -> Option<String> {
-    let hwnd = game_hwnd?;
-    match tokio::task::spawn_blocking(move || crate::overlay_capture::capture_window_png(hwnd))
+async fn capture_base64(game_hwnd: Option<i64>) -> Option<Arc<str>> {
+    capture_screenshot(game_hwnd?)
         .await
-    {
-        Ok(Ok(png)) => Some(base64::engine::general_purpose::STANDARD.encode(png)),
-        Ok(Err(error)) => {
-            tracing::warn!("screenshot capture failed: {error}");
-            None
-        }
-        Err(error) => {
-            tracing::warn!("screenshot capture task failed: {error}");
-            None
+        .inspect_err(|error| tracing::warn!("screenshot capture failed: {error}"))
+        .ok()
+}
+
+/// Capture a game window off the async runtime and base64-encode the PNG. The
+/// PNG bytes are dropped once encoded; the text is shared from then on.
+pub async fn capture_screenshot(game_hwnd: i64) -> Result<Arc<str>, String> {
+    let png = capture_png(game_hwnd).await?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(png).into())
+}
+
+/// Re-encode base64 PNG screenshots at half their size, off the async runtime.
+async fn shrink_screenshots(screenshots: Vec<Arc<str>>) -> Result<Vec<Arc<str>>, String> {
+    tokio::task::spawn_blocking(move || {
+        let engine = base64::engine::general_purpose::STANDARD;
+        screenshots
+            .iter()
+            .map(|screenshot| {
+                let png = engine
+                    .decode(screenshot.as_bytes())
+                    .map_err(|error| format!("invalid screenshot data: {error}"))?;
+                Ok(engine
+                    .encode(crate::overlay_capture::shrink_png(&png)?)
+                    .into())
+            })
+            .collect()
+    })
+    .await
+    .map_err(|error| format!("capture task failed: {error}"))?
+}
+
+/// Capture the game window as PNG bytes off the async runtime.
+async fn capture_png(game_hwnd: i64) -> Result<Vec<u8>, String> {
+    tokio::task::spawn_blocking(move || {
+        crate::overlay_capture::capture_window_png(game_hwnd).map(|png| png.bytes)
+    })
+    .await
+    .map_err(|error| format!("capture task failed: {error}"))?
+}
+
+/// The companion persona prompt (named by `[overlay] assistant_name`),
+/// optionally grounded with the detected game name. A matching `[[games]]` entry
+/// in config.toml replaces the persona prompt.
+fn build_system_prompt(game: Option<&GameInfo>, config: &crate::config::Config) -> String {
+    let entry = game.and_then(|game| config.game_for_exe(&game.exe));
+    let mut prompt = match entry {
+        Some(entry) if !entry.system_prompt.trim().is_empty() => {
+            entry.system_prompt.trim().to_owned()
         }
+        _ => default_system_prompt(config.overlay.assistant_name()),
+    };
+    if let Some(name) = game
+        .map(|game| game.name.trim())
+        .filter(|name| !name.is_empty())
+    {
+        let _ = write!(prompt, " The player is currently playing {name}.");
     }
+    prompt
 }
 
-/// The Sage persona prompt, optionally grounded with the detected game name.
-fn build_system_prompt(game: Option<&GameInfo>) -> String {
-    let mut prompt = default_system_prompt();
-    if let Some(game) = game {
-        let name = if game.title.trim().is_empty() {
-            std::path::Path::new(&game.exe)
-                .file_stem()
-                .map(|stem| stem.to_string_lossy().into_owned())
-                .unwrap_or_default()
-        } else {
-            game.title.trim().to_owned()
+/// Append the game's recent log lines (see `ContextLog`) to a system prompt.
+fn append_log_tail(prompt: &mut String, tail: &str) {
+    let _ = write!(
+        prompt,
+        "\n\nRecent lines from the game's own log, oldest first (context only; \
+         mention them only when relevant):\n{tail}"
+    );
+}
+
+/// The attached game's `[[games]] context_log`, copied out of the config so it
+/// can be read without holding the game lock.
+#[derive(Debug)]
+struct ContextLog {
+    path: String,
+    lines: usize,
+}
+
+impl ContextLog {
+    fn for_game(game: Option<&GameInfo>, config: &crate::config::Config) -> Option<Self> {
+        let entry = config.game_for_exe(&game?.exe)?;
+        let path = entry.context_log.trim();
+        (!path.is_empty()).then(|| Self {
+            path: path.to_owned(),
+            lines: entry.log_tail_lines(),
+        })
+    }
+
+    /// The log's last lines, or `None` when it cannot be read (missing, locked,
+    /// empty) -- a log is a nice-to-have, so a question never fails over it.
+    /// Blocking file I/O.
+    fn tail(&self) -> Option<String> {
+        use std::io::{Read as _, Seek as _, SeekFrom};
+
+        let read = || -> std::io::Result<Vec<u8>> {
+            let mut file = std::fs::File::open(&self.path)?;
+            let len = file.metadata()?.len();
+            file.seek(SeekFrom::Start(len.saturating_sub(LOG_TAIL_BYTES)))?;
+            let mut bytes = Vec::new();
+            file.read_to_end(&mut bytes)?;
+            Ok(bytes)
         };
-        if !name.is_empty() {
-            let _ = write!(prompt, " The player is currently playing {name}.");
+        match read() {
+            Ok(bytes) => last_lines(&String::from_utf8_lossy(&bytes), self.lines),
+            Err(e) => {
+                tracing::debug!("context_log {} skipped: {e}", self.path);
+                None
+            }
         }
     }
-    prompt
-}
 
-fn default_system_prompt() -> String {
-    "You are Sage, a sharp and knowledgeable game companion embedded in the player's screen. \
-     Keep answers short -- 2-3 sentences unless the player asks for detail. \
-     Never repeat or rephrase what the player just said. \
-     Never state the obvious (e.g. don't say \"I see you're in a menu\"). \
-     Jump straight to the useful part: what to do, where to go, or how something works. \
-     When you see a screenshot, focus only on what's relevant to the player's question. \
-     If no question is asked with a screenshot, give the single most useful observation."
-        .to_owned()
+    fn estimated_tokens(&self) -> usize {
+        self.lines * LOG_LINE_TOKENS
+    }
 }
 
-const TRANSLATE_SYSTEM: &str =
-    "You are a screen translator for a gamer. Read the foreign text in the image and translate it \
-     into natural English. Be concise; do not add commentary.";
+/// The last `count` non-blank lines of `text`, joined by newlines.
+fn last_lines(text: &str, count: usize) -> Option<String> {
+    let mut lines: Vec<&str> = text
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.trim().is_empty())
+        .rev()
+        .take(count)
+        .collect();
+    lines.reverse();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
 
-/// Capture the game window and translate any foreign text in it to English via
-/// Gemini. A one-shot call, independent of the chat request slot.
-pub async fn translate_capture(game_hwnd: i64) -> Result<String, String> {
-    let png =
-        tokio::task::spawn_blocking(move || crate::overlay_capture::capture_window_png(game_hwnd))
-            .await
-            .map_err(|error| format!("capture task failed: {error}"))??;
-    let screenshot = base64::engine::general_purpose::STANDARD.encode(png);
-    let cfg = gemini::load_config()?;
-    let messages = [ChatMessage {
-        role: "user".to_owned(),
-        content: "Translate any non-English text visible in this screenshot into English. Output \
-                  only the translation. If there is no foreign text, reply exactly: No foreign \
-                  text found."
-            .to_owned(),
-    }];
-    let mut out = String::new();
-    gemini::stream(
-        &messages,
-        TRANSLATE_SYSTEM,
-        Some(screenshot),
-        &cfg.model,
-        &cfg.api_key,
-        |chunk| {
-            out.push_str(&chunk);
-            Ok(())
-        },
+fn default_system_prompt(name: &str) -> String {
+    format!(
+        "You are {name}, a sharp and knowledgeable game companion embedded in the player's screen. \
+         Keep answers short -- 2-3 sentences unless the player asks for detail. \
+         Never repeat or rephrase what the player just said. \
+         Never state the obvious (e.g. don't say \"I see you're in a menu\"). \
+         Jump straight to the useful part: what to do, where to go, or how something works. \
+         When you see a screenshot, focus only on what's relevant to the player's question. \
+         If no question is asked with a screenshot, give the single most useful observation."
     )
-    .await?;
-    Ok(out.trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{
+        build_system_prompt, coalesce, default_system_prompt, estimate_tokens, last_lines,
+        trim_history, AiState, ChatMessage, ContextLog, GameInfo, ReplyLength, TrimStrategy,
+        IMAGE_TOKENS, LOG_LINE_TOKENS, MAX_STAGED,
+    };
+
+    fn msg(role: &str, content: &str) -> ChatMessage {
+        ChatMessage {
+            role: role.to_owned(),
+            content: content.to_owned(),
+        }
+    }
+
+    fn roles(messages: &[ChatMessage]) -> Vec<&str> {
+        messages.iter().map(|m| m.role.as_str()).collect()
+    }
+
+    #[test]
+    fn reply_length_sets_prompt_hint_and_token_cap() {
+        assert_eq!(ReplyLength::Medium.apply("Base.".to_owned()), "Base.");
+        assert_eq!(
+            ReplyLength::Short.apply("Base.".to_owned()),
+            "Base.\n\nRespond in 1-2 sentences."
+        );
+        assert!(ReplyLength::Long
+            .apply("Base.".to_owned())
+            .starts_with("Base.\n\nBe thorough"));
+        assert_eq!(
+            ReplyLength::Short.max_output_tokens(),
+            ReplyLength::Medium.max_output_tokens()
+        );
+        assert!(ReplyLength::Long.max_output_tokens() > ReplyLength::Medium.max_output_tokens());
+        assert_eq!(
+            serde_json::from_str::<ReplyLength>("\"short\"").unwrap(),
+            ReplyLength::Short
+        );
+    }
+
+    #[tokio::test]
+    async fn coalesce_batches_chunks_within_the_interval() {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel::<String>();
+        tx.send("a".to_owned()).unwrap();
+        let producer = tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            tx.send("b".to_owned()).unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            tx.send("c".to_owned()).unwrap();
+        });
+        let mut sent = Vec::new();
+        coalesce(&mut rx, std::time::Duration::from_millis(500), |batch| {
+            sent.push(batch);
+        })
+        .await;
+        producer.await.unwrap();
+        assert_eq!(sent, ["a", "bc"]);
+    }
+
+    #[test]
+    fn trim_keeps_short_histories_intact() {
+        let mut messages = vec![msg("user", "q1"), msg("assistant", "a1"), msg("user", "q2")];
+        trim_history(&mut messages, 50, TrimStrategy::DropOldest);
+        assert_eq!(messages.len(), 3);
+    }
+
+    #[test]
+    fn trim_zero_disables_trimming() {
+        let mut messages: Vec<_> = (0..200).map(|_| msg("user", "q")).collect();
+        trim_history(&mut messages, 0, TrimStrategy::KeepFirstAndRecent);
+        assert_eq!(messages.len(), 200);
+    }
+
+    #[test]
+    fn trim_keeps_most_recent_messages_starting_on_user() {
+        let mut messages = vec![
+            msg("user", "q1"),
+            msg("assistant", "a1"),
+            msg("user", "q2"),
+            msg("assistant", "a2"),
+            msg("user", "q3"),
+        ];
+        // A cut at 4 would start on "a1"; it moves forward to "q2".
+        trim_history(&mut messages, 4, TrimStrategy::DropOldest);
+        assert_eq!(roles(&messages), ["user", "assistant", "user"]);
+        assert_eq!(messages[0].content, "q2");
+    }
+
+    #[test]
+    fn trim_can_keep_the_opening_exchange() {
+        let conversation: Vec<_> = (1..=4)
+            .flat_map(|n| {
+                [
+                    msg("user", &format!("q{n}")),
+                    msg("assistant", &format!("a{n}")),
+                ]
+            })
+            .chain([msg("user", "q5")])
+            .collect();
+
+        let mut messages = conversation.clone();
+        trim_history(&mut messages, 5, TrimStrategy::KeepFirstAndRecent);
+        let contents: Vec<_> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["q1", "a1", "q4", "a4", "q5"]);
+
+        // The recent slice still starts on a user turn: 4 leaves room for
+        // "a4, q5", which moves forward to just "q5".
+        let mut messages = conversation.clone();
+        trim_history(&mut messages, 4, TrimStrategy::KeepFirstAndRecent);
+        let contents: Vec<_> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["q1", "a1", "q5"]);
+
+        // Too small a budget to keep the pair beside a question: drop oldest.
+        let mut messages = conversation;
+        trim_history(&mut messages, 2, TrimStrategy::KeepFirstAndRecent);
+        assert_eq!(roles(&messages), ["user"]);
+    }
+
+    #[test]
+    fn trim_strategy_parses_config_values() {
+        assert_eq!(TrimStrategy::from_config(""), TrimStrategy::DropOldest);
+        assert_eq!(
+            TrimStrategy::from_config(" Keep_First_And_Recent "),
+            TrimStrategy::KeepFirstAndRecent
+        );
+        assert_eq!(
+            TrimStrategy::from_config("summarize"),
+            TrimStrategy::DropOldest
+        );
+    }
+
+    #[test]
+    fn last_lines_skips_blanks_and_keeps_order() {
+        let text = "boot\n\nquest started\r\nboss spawned\n\n";
+        assert_eq!(
+            last_lines(text, 2).as_deref(),
+            Some("quest started\nboss spawned")
+        );
+        assert_eq!(
+            last_lines(text, 10).as_deref(),
+            Some("boot\nquest started\nboss spawned")
+        );
+        assert_eq!(last_lines("\n  \n", 5), None);
+    }
+
+    #[test]
+    fn context_log_tail_is_read_and_missing_logs_are_skipped() {
+        let path =
+            std::env::temp_dir().join(format!("aigc_context_log_{}.log", std::process::id()));
+        std::fs::write(&path, "line 1\nline 2\nline 3\n").unwrap();
+        let config: crate::config::Config = toml::from_str(&format!(
+            "[[games]]\nprocess = \"sekiro.exe\"\ncontext_log = {:?}\ncontext_log_lines = 2\n",
+            path.display().to_string()
+        ))
+        .unwrap();
+        let log = ContextLog::for_game(Some(&game(r"C:\g\sekiro.exe", "Sekiro")), &config)
+            .expect("sekiro has a context log");
+        assert_eq!(log.tail().as_deref(), Some("line 2\nline 3"));
+        assert_eq!(log.estimated_tokens(), 2 * LOG_LINE_TOKENS);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(log.tail(), None);
+        assert!(ContextLog::for_game(Some(&game(r"C:\g\witness.exe", "")), &config).is_none());
+    }
+
+    fn game(exe: &str, name: &str) -> GameInfo {
+        GameInfo {
+            exe: exe.to_owned(),
+            name: name.to_owned(),
+            ..GameInfo::default()
+        }
+    }
+
+    #[test]
+    fn system_prompt_prefers_matching_game_override() {
+        let config: crate::config::Config = toml::from_str(
+            "[[games]]\nprocess = \"sekiro.exe\"\nsystem_prompt = \"Parry tips only.\"\n",
+        )
+        .unwrap();
+        let prompt = build_system_prompt(Some(&game(r"C:\g\sekiro.exe", "Sekiro")), &config);
+        assert_eq!(
+            prompt,
+            "Parry tips only. The player is currently playing Sekiro."
+        );
+    }
+
+    #[test]
+    fn system_prompt_falls_back_to_default_without_override() {
+        let config: crate::config::Config =
+            toml::from_str("[[games]]\nprocess = \"sekiro.exe\"\nsystem_prompt = \"Parry.\"\n")
+                .unwrap();
+        let prompt = build_system_prompt(Some(&game(r"C:\g\witness.exe", "The Witness")), &config);
+        assert!(prompt.starts_with(&default_system_prompt("Sage")));
+        assert!(prompt.ends_with(" The player is currently playing The Witness."));
+    }
+
+    #[test]
+    fn staging_is_capped_and_drains_only_sent_screenshots() {
+        let ai = AiState::default();
+        for count in 1..=MAX_STAGED {
+            assert_eq!(ai.stage(format!("shot{count}").into()), Ok(count));
+        }
+        assert!(ai.stage("extra".into()).is_err());
+        assert_eq!(ai.unstage(0), MAX_STAGED - 1);
+        assert_eq!(ai.unstage(99), MAX_STAGED - 1);
+        ai.drain_staged(2);
+        assert_eq!(*ai.staged.lock(), [Arc::from("shot4")]);
+    }
+
+    #[test]
+    fn estimate_counts_prompt_and_history_characters() {
+        let messages = [msg("user", "abcd"), msg("assistant", "efgh")];
+        assert_eq!(estimate_tokens("ijkl", &messages, 0), 3);
+    }
+
+    #[test]
+    fn estimate_rounds_partial_tokens_up() {
+        assert_eq!(estimate_tokens("", &[msg("user", "a")], 0), 1);
+        assert_eq!(estimate_tokens("", &[], 0), 0);
+    }
+
+    #[test]
+    fn estimate_adds_flat_cost_per_image() {
+        assert_eq!(estimate_tokens("", &[msg("user", "")], 2), 2 * IMAGE_TOKENS);
+    }
 }
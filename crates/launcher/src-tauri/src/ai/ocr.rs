@@ -0,0 +1,94 @@
+//! Local OCR for the translate hotkey (`[translation] provider = "ocr"`): pipes
+//! the captured PNG through the Tesseract CLI and returns the raw text, with no
+//! model, key, or network involved.
+
+use std::process::Stdio;
+
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use crate::config::TranslationConfig;
+
+/// Tesseract binary used when `[translation] tesseract_path` is empty.
+const DEFAULT_TESSERACT: &str = "tesseract";
+
+/// Command-line arguments: read the image from stdin, write text to stdout,
+/// optionally restricted to the configured `-l` language packs.
+fn tesseract_args(config: &TranslationConfig) -> Vec<String> {
+    let mut args = vec!["stdin".to_owned(), "stdout".to_owned()];
+    let languages = config.ocr_languages.trim();
+    if !languages.is_empty() {
+        args.push("-l".to_owned());
+        args.push(languages.to_owned());
+    }
+    args
+}
+
+/// Extract the text in `png` with Tesseract.
+pub async fn extract_text(png: Vec<u8>, config: &TranslationConfig) -> Result<String, String> {
+    let program = match config.tesseract_path.trim() {
+        "" => DEFAULT_TESSERACT,
+        path => path,
+    };
+    let mut cmd = Command::new(program);
+    cmd.args(tesseract_args(config))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    super::cli::no_window(&mut cmd);
+
+    let mut child = cmd.spawn().map_err(|e| {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            "Tesseract OCR is not installed. Install it, or set [translation] tesseract_path \
+             in config.toml."
+                .to_owned()
+        } else {
+            format!("Failed to start Tesseract: {e}")
+        }
+    })?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(&png)
+            .await
+            .map_err(|e| format!("Failed to send the capture to Tesseract: {e}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("Tesseract failed: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let detail = stderr.lines().find(|line| !line.trim().is_empty());
+        return Err(format!(
+            "Tesseract failed: {}",
+            detail.unwrap_or("no error output").trim()
+        ));
+    }
+    let text = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    if text.is_empty() {
+        return Ok("No text found.".to_owned());
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::tesseract_args;
+    use crate::config::TranslationConfig;
+
+    #[test]
+    fn languages_are_passed_only_when_configured() {
+        let config = TranslationConfig::default();
+        assert_eq!(tesseract_args(&config), ["stdin", "stdout"]);
+
+        let config = TranslationConfig {
+            ocr_languages: " jpn+eng ".to_owned(),
+            ..TranslationConfig::default()
+        };
+        assert_eq!(
+            tesseract_args(&config),
+            ["stdin", "stdout", "-l", "jpn+eng"]
+        );
+    }
+}
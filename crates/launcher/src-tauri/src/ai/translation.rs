@@ -0,0 +1,273 @@
+//! Screen translation: capture the game frame, ask Gemini to translate its
+//! foreign text (or extract it locally with OCR), and remember recent results
+//! so re-pressing the hotkey on a static menu does not re-send the same image.
+
+use std::collections::VecDeque;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use base64::Engine as _;
+use serde::Serialize;
+
+use super::{capture_png, gemini, ocr, AiState, ChatMessage};
+use crate::config::TranslationConfig;
+use crate::state::AppState;
+
+/// Small least-recently-used cache of translations keyed by a hash of the
+/// captured PNG and the configured languages. Only byte-identical frames hit:
+/// a static menu re-encodes to the same PNG, while any on-screen change misses.
+#[derive(Default)]
+pub struct TranslationCache {
+    /// Oldest first; a hit moves the entry to the back.
+    entries: VecDeque<(u64, String)>,
+}
+
+impl TranslationCache {
+    fn get(&mut self, key: u64) -> Option<String> {
+        let index = self.entries.iter().position(|(k, _)| *k == key)?;
+        let entry = self.entries.remove(index)?;
+        let text = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(text)
+    }
+
+    /// Store a translation, evicting the least recently used beyond `capacity`
+    /// (`[translation] cache_size`; 0 disables caching).
+    fn insert(&mut self, key: u64, text: String, capacity: usize) {
+        self.entries.retain(|(k, _)| *k != key);
+        if capacity == 0 {
+            return;
+        }
+        self.entries.push_back((key, text));
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+/// Which engine answers the translate hotkey (`[translation] provider`).
+/// Serialized lowercase for the overlay's Translate tab.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranslationProvider {
+    #[default]
+    Gemini,
+    /// Local Tesseract OCR: returns the raw on-screen text, untranslated.
+    Ocr,
+}
+
+impl TranslationProvider {
+    pub fn from_config(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" | "gemini" => Self::Gemini,
+            "ocr" => Self::Ocr,
+            other => {
+                tracing::warn!(
+                    "config.toml [translation] provider = {other:?} is not gemini/ocr; using gemini"
+                );
+                Self::Gemini
+            }
+        }
+    }
+}
+
+fn cache_key(
+    provider: TranslationProvider,
+    languages: &TranslationConfig,
+    target: &str,
+    screenshot: &[u8],
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    provider.hash(&mut hasher);
+    languages.source().hash(&mut hasher);
+    target.hash(&mut hasher);
+    screenshot.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The translator system prompt and user instruction for the configured
+/// source language and the active `target`. With an auto source any foreign
+/// text is translated; a fixed source restricts the model to text in that
+/// language.
+fn translation_prompts(languages: &TranslationConfig, target: &str) -> (String, String) {
+    let Some(source) = languages.source() else {
+        return (
+            format!(
+                "You are a screen translator for a gamer. Read the foreign text in the image and \
+                 translate it into natural {target}. Be concise; do not add commentary."
+            ),
+            format!(
+                "Translate any non-{target} text visible in this screenshot into {target}. Output \
+                 only the translation. If there is no foreign text, reply exactly: No foreign \
+                 text found."
+            ),
+        );
+    };
+    (
+        format!(
+            "You are a screen translator for a gamer. Read the {source} text in the image and \
+             translate it into natural {target}. Be concise; do not add commentary."
+        ),
+        format!(
+            "Translate only the {source} text visible in this screenshot into {target}; ignore \
+             text in any other language. Output only the translation. If there is no {source} \
+             text, reply exactly: No {source} text found."
+        ),
+    )
+}
+
+/// Capture the game window and translate its foreign text (`[translation]`
+/// source language and the target picked by the cycle hotkey, default any ->
+/// English) via Gemini, or extract it with local OCR.
+/// A one-shot call, independent of the chat request slot. An unchanged frame is
+/// answered from the cache without spending quota.
+pub async fn translate_capture(
+    ai: &AiState,
+    state: &AppState,
+    game_hwnd: i64,
+) -> Result<String, String> {
+    ai.ensure_running()?;
+    let png = capture_png(game_hwnd).await?;
+    let config = crate::config::load().translation;
+    let provider = TranslationProvider::from_config(&config.provider);
+    let target = config.active_target(state.translation_target()).to_owned();
+    let key = cache_key(provider, &config, &target, &png);
+    if let Some(text) = ai.translations.lock().get(key) {
+        tracing::debug!("translation served from cache");
+        return Ok(text);
+    }
+
+    let text = match provider {
+        TranslationProvider::Gemini => {
+            let text = translate_with_gemini(png, &config, &target, &state.gemini_model()).await?;
+            state.record_gemini_request();
+            text
+        }
+        TranslationProvider::Ocr => ocr::extract_text(png, &config).await?,
+    };
+    ai.translations
+        .lock()
+        .insert(key, text.clone(), config.cache_size);
+    Ok(text)
+}
+
+async fn translate_with_gemini(
+    png: Vec<u8>,
+    config: &TranslationConfig,
+    target: &str,
+    model: &str,
+) -> Result<String, String> {
+    let cfg = gemini::load_config(model)?;
+    let (system, instruction) = translation_prompts(config, target);
+    let messages = [ChatMessage {
+        role: "user".to_owned(),
+        content: instruction,
+    }];
+    let screenshot = base64::engine::general_purpose::STANDARD.encode(png);
+    let mut out = String::new();
+    gemini::stream(&messages, &system, vec![screenshot.into()], &cfg, |chunk| {
+        out.push_str(&chunk);
+        Ok(())
+    })
+    .await?;
+    Ok(out.trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{cache_key, translation_prompts, TranslationCache, TranslationProvider};
+    use crate::config::TranslationConfig;
+
+    #[test]
+    fn auto_source_translates_any_foreign_text() {
+        let languages = TranslationConfig::default();
+        let (system, instruction) = translation_prompts(&languages, languages.target());
+        assert!(system.contains("foreign text"));
+        assert!(instruction.contains("non-English text"));
+        assert!(instruction.ends_with("No foreign text found."));
+    }
+
+    #[test]
+    fn fixed_source_restricts_translation_to_that_language() {
+        let languages = TranslationConfig {
+            source_language: "German".to_owned(),
+            ..TranslationConfig::default()
+        };
+        let (system, instruction) = translation_prompts(&languages, "Polish");
+        assert!(system.contains("Read the German text"));
+        assert!(instruction.contains("Translate only the German text"));
+        assert!(instruction.contains("into Polish"));
+        assert!(instruction.ends_with("No German text found."));
+    }
+
+    #[test]
+    fn cache_evicts_least_recently_used() {
+        let mut cache = TranslationCache::default();
+        cache.insert(1, "one".to_owned(), 2);
+        cache.insert(2, "two".to_owned(), 2);
+        assert_eq!(cache.get(1).as_deref(), Some("one"));
+        cache.insert(3, "three".to_owned(), 2);
+        assert_eq!(cache.get(2), None);
+        assert_eq!(cache.get(1).as_deref(), Some("one"));
+        assert_eq!(cache.get(3).as_deref(), Some("three"));
+
+        cache.clear();
+        assert_eq!(cache.get(1), None);
+    }
+
+    #[test]
+    fn zero_capacity_disables_the_cache() {
+        let mut cache = TranslationCache::default();
+        cache.insert(1, "one".to_owned(), 0);
+        assert_eq!(cache.get(1), None);
+    }
+
+    #[test]
+    fn cache_key_depends_on_languages_and_frame() {
+        let auto = TranslationConfig::default();
+        let german = TranslationConfig {
+            source_language: "German".to_owned(),
+            ..TranslationConfig::default()
+        };
+        let gemini = TranslationProvider::Gemini;
+        assert_eq!(
+            cache_key(gemini, &auto, "English", b"frame"),
+            cache_key(gemini, &auto, "English", b"frame")
+        );
+        assert_ne!(
+            cache_key(gemini, &auto, "English", b"frame"),
+            cache_key(gemini, &auto, "English", b"other")
+        );
+        assert_ne!(
+            cache_key(gemini, &auto, "English", b"frame"),
+            cache_key(gemini, &german, "English", b"frame")
+        );
+        assert_ne!(
+            cache_key(gemini, &auto, "English", b"frame"),
+            cache_key(gemini, &auto, "Polish", b"frame")
+        );
+        assert_ne!(
+            cache_key(gemini, &auto, "English", b"frame"),
+            cache_key(TranslationProvider::Ocr, &auto, "English", b"frame")
+        );
+    }
+
+    #[test]
+    fn provider_defaults_to_gemini() {
+        assert_eq!(
+            TranslationProvider::from_config(""),
+            TranslationProvider::Gemini
+        );
+        assert_eq!(
+            TranslationProvider::from_config(" OCR "),
+            TranslationProvider::Ocr
+        );
+        assert_eq!(
+            TranslationProvider::from_config("llava"),
+            TranslationProvider::Gemini
+        );
+    }
+}
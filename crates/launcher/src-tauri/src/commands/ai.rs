@@ -1,10 +1,13 @@
 //! Overlay AI commands: streaming dispatch, cancellation, provider availability,
-//! and persisting the selected provider.
+//! and persisting the selected provider and reply length.
 
 use tauri::ipc::Channel;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Manager, State};
 
-use crate::ai::{AiState, ChatMessage, Provider, ProviderAvailability, RequestParams, SageEvent};
+use crate::ai::{
+    AiState, ChatMessage, Provider, ProviderAvailability, ReplyLength, RequestParams, SageEvent,
+    TokenEstimate,
+};
 use crate::state::AppState;
 
 /// Report which providers can currently serve a request (for the UI dropdown).
@@ -25,6 +28,7 @@ pub fn ask_sage(
     provider: Provider,
     messages: Vec<ChatMessage>,
     attach_screenshot: bool,
+    reply_length: ReplyLength,
     channel: Channel<SageEvent>,
 ) {
     crate::ai::spawn_request(
@@ -35,11 +39,24 @@ pub fn ask_sage(
             provider,
             messages,
             attach_screenshot,
+            reply_length,
         },
         channel,
     );
 }
 
+/// Estimate the input size of the request the overlay is about to send, so it
+/// can warn before an oversized payload is rejected upstream.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn estimate_request(
+    app: AppHandle,
+    messages: Vec<ChatMessage>,
+    attach_screenshot: bool,
+) -> TokenEstimate {
+    crate::ai::estimate_request(&app, &messages, attach_screenshot)
+}
+
 /// Cancel the in-flight request if it matches `request_id` (Stop button).
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
@@ -60,16 +77,128 @@ pub fn set_active_provider(provider: Provider, state: State<'_, AppState>) -> Re
     state.save()
 }
 
+/// Persist the overlay's reply-length preference.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn set_reply_length(length: ReplyLength, state: State<'_, AppState>) -> Result<(), String> {
+    {
+        let mut launcher = state.launcher.lock();
+        length
+            .as_str()
+            .clone_into(&mut launcher.settings.overlay_reply_length);
+    }
+    state.save()
+}
+
+#[derive(serde::Serialize)]
+pub struct GeminiUsage {
+    pub today: u32,
+    /// `[api] daily_limit`; 0 when unset.
+    pub daily_limit: u32,
+}
+
+/// Gemini requests sent today, for the overlay's daily counter.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn gemini_usage(state: State<'_, AppState>) -> GeminiUsage {
+    GeminiUsage {
+        today: state.gemini_requests_today(),
+        daily_limit: crate::config::load().api.daily_limit,
+    }
+}
+
 #[derive(serde::Serialize)]
 pub struct TranslateResult {
     pub text: String,
 }
 
-/// Capture the detected game window and translate its on-screen foreign text to
-/// English. One-shot (not part of the streaming chat slot).
+/// Capture the detected game window now and stage it for the next question.
+/// Returns the number of staged screenshots.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub async fn stage_screenshot(
+    ai: State<'_, AiState>,
+    overlay: State<'_, crate::overlay::OverlayState>,
+) -> Result<usize, String> {
+    ai.ensure_running()?;
+    let hwnd = overlay
+        .game
+        .lock()
+        .as_ref()
+        .map(|game| game.hwnd)
+        .ok_or_else(|| "No game detected -- open the overlay over a game first.".to_owned())?;
+    let screenshot = crate::ai::capture_screenshot(hwnd).await?;
+    ai.stage(screenshot)
+}
+
+/// The staged screenshot at `index` as base64 PNG, for the overlay's
+/// `[capture] confirm_before_send` preview.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn staged_screenshot(ai: State<'_, AiState>, index: usize) -> Result<String, String> {
+    ai.staged_at(index)
+        .map(|screenshot| screenshot.to_string())
+        .ok_or_else(|| "That screenshot is no longer staged.".to_owned())
+}
+
+/// Remove one staged screenshot (its X in the overlay). Returns the new count.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn unstage_screenshot(ai: State<'_, AiState>, index: usize) -> usize {
+    ai.unstage(index)
+}
+
+/// Drop every staged screenshot (new chat).
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn clear_staged_screenshots(ai: State<'_, AiState>) {
+    ai.clear_staged();
+}
+
+/// Forget cached screen translations (new chat).
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn clear_translation_cache(ai: State<'_, AiState>) {
+    ai.clear_translations();
+}
+
+/// Export the overlay's current conversation to a timestamped markdown file in
+/// the launcher's data folder (`exports/`). Returns the written path.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn export_conversation(
+    app: AppHandle,
+    overlay: State<'_, crate::overlay::OverlayState>,
+    messages: Vec<ChatMessage>,
+) -> Result<String, String> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Cannot determine data folder: {e}"))?
+        .join("exports");
+    let game = overlay
+        .game
+        .lock()
+        .as_ref()
+        .map(|game| game.name.clone())
+        .unwrap_or_default();
+    let overlay_config = crate::config::load().overlay;
+    let speakers = crate::ai::Speakers {
+        user: overlay_config.user_name(),
+        assistant: overlay_config.assistant_name(),
+    };
+    let path = crate::ai::export_conversation(&dir, &game, &speakers, &messages)?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Capture the detected game window and translate its on-screen foreign text
+/// (languages from `[translation]`). One-shot (not part of the streaming chat slot).
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
 pub async fn translate_screen(
+    app: AppHandle,
+    ai: State<'_, AiState>,
+    state: State<'_, AppState>,
     overlay: State<'_, crate::overlay::OverlayState>,
 ) -> Result<TranslateResult, String> {
     let hwnd = overlay
@@ -78,10 +207,39 @@ pub async fn translate_screen(
         .as_ref()
         .map(|game| game.hwnd)
         .ok_or_else(|| "No game detected -- open the overlay over a game first.".to_owned())?;
-    let text = crate::ai::translate_capture(hwnd).await?;
+    let text = crate::ai::translate_capture(&ai, &state, hwnd).await?;
+    let translation = crate::config::load().translation;
+    if translation.subtitle_bar {
+        crate::subtitles::show(&app, &text, translation.subtitle_secs);
+    }
     Ok(TranslateResult { text })
 }
 
+#[derive(serde::Serialize)]
+pub struct TranslationLanguages {
+    /// `None` when auto-detecting.
+    pub source: Option<String>,
+    /// The target picked by the cycle hotkey.
+    pub target: String,
+    /// Gemini needs a key; OCR runs locally and only extracts the text.
+    pub provider: crate::ai::TranslationProvider,
+}
+
+/// The translate hotkey's current languages and provider, for the overlay's
+/// Translate tab.
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn translation_languages(state: State<'_, AppState>) -> TranslationLanguages {
+    let translation = crate::config::load().translation;
+    TranslationLanguages {
+        source: translation.source().map(str::to_owned),
+        target: translation
+            .active_target(state.translation_target())
+            .to_owned(),
+        provider: crate::ai::TranslationProvider::from_config(&translation.provider),
+    }
+}
+
 /// Store (or clear, when empty) the Gemini API key in OS secret storage. Returns
 /// the refreshed availability so the UI can flip the Gemini pill without a
 /// restart. The key is never returned or logged.
@@ -15,11 +15,12 @@ pub fn get_settings(state: State<'_, AppState>) -> LauncherSettings {
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
 pub fn update_settings(
-    settings: LauncherSettings,
+    mut settings: LauncherSettings,
     state: State<'_, AppState>,
     app: AppHandle,
 ) -> Result<(), String> {
     let launch_on_startup = settings.launch_on_startup;
+    settings.overlay_opacity = settings.overlay_opacity.clamp(0.0, 1.0);
     {
         let mut launcher = state.launcher.lock();
         launcher.settings = settings;
@@ -36,7 +37,66 @@ pub fn update_settings(
     state.save()
 }
 
-/// Open an https URL in the default browser (Settings "Get a key" / docs links).
+/// Overlay theme and chat colors from `config.toml` `[overlay]` (plus
+/// `[capture] confirm_before_send`), re-read each time the overlay opens.
+#[tauri::command]
+pub fn get_overlay_appearance() -> crate::config::OverlayAppearance {
+    let config = crate::config::load();
+    crate::config::OverlayAppearance {
+        confirm_before_send: config.capture.confirm_before_send,
+        ..config.overlay.appearance()
+    }
+}
+
+/// One-glance health check for Settings -> About (and bug-report screenshots).
+#[derive(serde::Serialize)]
+pub struct Diagnostics {
+    pub config: crate::config::FileStatus,
+    pub config_path: String,
+    /// Likely mistakes in config.toml (see `Config::problems`).
+    pub config_problems: Vec<String>,
+    pub gemini_key: crate::ai::KeySource,
+    pub gemini_model: String,
+    /// The game the overlay last opened over, as "Name (exe)".
+    pub game: Option<String>,
+    /// Kill switch engaged.
+    pub paused: bool,
+}
+
+#[tauri::command]
+#[allow(clippy::needless_pass_by_value)]
+pub fn get_diagnostics(
+    state: State<'_, AppState>,
+    ai: State<'_, crate::ai::AiState>,
+    overlay: State<'_, crate::overlay::OverlayState>,
+) -> Diagnostics {
+    let config = crate::config::load();
+    let game = overlay.game.lock().as_ref().map(|game| {
+        let exe = game.exe.rsplit(['\\', '/']).next().unwrap_or_default();
+        format!("{} ({exe})", game.name)
+    });
+    Diagnostics {
+        config: crate::config::status(),
+        config_problems: config.problems(),
+        config_path: crate::config::path()
+            .map(|path| path.display().to_string())
+            .unwrap_or_default(),
+        gemini_key: crate::ai::gemini_key_source(&config),
+        gemini_model: crate::ai::gemini_model(&config, &state.gemini_model()),
+        game,
+        paused: ai.is_paused(),
+    }
+}
+
+/// Write a commented starter `config.toml` at `config::path()` (never over an
+/// existing one) and return its path.
+#[tauri::command]
+pub fn create_config_file() -> Result<String, String> {
+    crate::config::create_template().map(|path| path.display().to_string())
+}
+
+/// Open an https URL in the default browser (Settings "Get a key" / docs links,
+/// overlay search citations).
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)]
 pub fn open_url(app: AppHandle, url: String) -> Result<(), String> {
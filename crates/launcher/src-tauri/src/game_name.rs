@@ -0,0 +1,378 @@
+//! Resolve a display name for the detected foreground game, used in the overlay
+//! header and injected into the system prompt. Tiers, best first: a `[[games]]`
+//! override in config.toml, the Steam library, the window title, the exe name.
+//! Steam sits above the title because engines often leave a generic window
+//! title (`UnrealWindow`).
+
+use std::path::Path;
+
+use crate::config::Config;
+use crate::models::{Game, GameSource};
+use crate::overlay::GameInfo;
+
+/// Series abbreviations kept upper-case when prettifying exe names, and split
+/// off a trailing numeral (`GTAV` -> "GTA V").
+const ACRONYMS: &[&str] = &["GTA", "RE", "FF", "COD", "NFS", "MGS", "DMC", "KOTOR"];
+
+/// Store and launcher names games append to their window titles after a dash
+/// (`Fortnite - Epic Games`); `[overlay] title_suffixes` adds more.
+const TITLE_SUFFIXES: &[&str] = &["Steam", "Epic Games", "Epic Games Store", "GOG Galaxy"];
+
+/// Words that introduce a trailing version number (`Build 1234`).
+const VERSION_WORDS: &[&str] = &["build", "version", "ver", "ver.", "v"];
+
+/// Best available name for `game`; empty only when nothing at all is known.
+pub fn detect(game: &GameInfo, config: &Config, library: &[Game]) -> String {
+    name_from_config(&game.exe, config)
+        .or_else(|| name_from_steam(&game.exe, library))
+        .or_else(|| non_empty(&clean_title(&game.title, &config.overlay.title_suffixes)))
+        .or_else(|| name_from_exe(&game.exe))
+        .unwrap_or_default()
+}
+
+/// Strip trailing decorations from a window title, repeatedly: a bracketed or
+/// parenthesized tag (`[DX12]`, `(GOG)`), a store name after a dash, and a
+/// version (`v1.2.3`, `Build 1234`). A title that is nothing but decoration is
+/// kept as it was.
+fn clean_title(title: &str, extra_suffixes: &[String]) -> String {
+    let original = title.trim();
+    let mut title = original;
+    loop {
+        let before = title;
+        title = strip_bracketed(title);
+        title = strip_store_suffix(title, extra_suffixes);
+        title = strip_version(title);
+        title = title.trim_end_matches(|c: char| c.is_whitespace() || is_title_separator(c));
+        if title == before {
+            break;
+        }
+    }
+    if title.is_empty() { original } else { title }.to_owned()
+}
+
+fn is_title_separator(character: char) -> bool {
+    matches!(character, '-' | '|' | ':' | '\u{2013}' | '\u{2014}')
+}
+
+/// `Elden Ring [DX12]` -> `Elden Ring `.
+fn strip_bracketed(title: &str) -> &str {
+    let open = match title.chars().last() {
+        Some(']') => '[',
+        Some(')') => '(',
+        _ => return title,
+    };
+    match title.rfind(open) {
+        Some(start) if start > 0 => &title[..start],
+        _ => title,
+    }
+}
+
+/// `Fortnite - Epic Games` -> `Fortnite - `; only after a separator, so a
+/// title that merely ends in the same word is left alone.
+fn strip_store_suffix<'a>(title: &'a str, extra: &[String]) -> &'a str {
+    let lower = title.to_lowercase();
+    let suffixes = TITLE_SUFFIXES
+        .iter()
+        .copied()
+        .chain(extra.iter().map(|suffix| suffix.trim()))
+        .filter(|suffix| !suffix.is_empty());
+    for suffix in suffixes {
+        if !lower.ends_with(&suffix.to_lowercase()) || title.len() < suffix.len() {
+            continue;
+        }
+        let Some(rest) = title.get(..title.len() - suffix.len()) else {
+            continue;
+        };
+        if rest.trim_end().ends_with(is_title_separator) {
+            return rest;
+        }
+    }
+    title
+}
+
+/// `Valheim v0.217.46` / `Game Build 1234` -> the name before the version. A
+/// bare number (`Cyberpunk 2077`) is part of the name and stays.
+fn strip_version(title: &str) -> &str {
+    let Some((rest, last)) = title.trim_end().rsplit_once(' ') else {
+        return title;
+    };
+    let number = last
+        .strip_prefix(['v', 'V'])
+        .unwrap_or(last)
+        .trim_end_matches('.');
+    let is_number = !number.is_empty()
+        && number.chars().all(|c| c.is_ascii_digit() || c == '.')
+        && number.starts_with(|c: char| c.is_ascii_digit());
+    if !is_number {
+        return title;
+    }
+    if let Some((name, word)) = rest.trim_end().rsplit_once(' ') {
+        if VERSION_WORDS.contains(&word.to_lowercase().as_str()) {
+            return name;
+        }
+    }
+    if number.contains('.') || last.starts_with(['v', 'V']) {
+        rest
+    } else {
+        title
+    }
+}
+
+fn name_from_config(exe: &str, config: &Config) -> Option<String> {
+    non_empty(&config.game_for_exe(exe)?.name)
+}
+
+/// A scanned library game containing the exe, else the app id from a
+/// `steam_appid.txt` beside it (looked up in the library, or labelled by id).
+fn name_from_steam(exe: &str, library: &[Game]) -> Option<String> {
+    name_from_library(exe, library).or_else(|| {
+        let dir = Path::new(exe).parent()?;
+        let contents = std::fs::read_to_string(dir.join("steam_appid.txt")).ok()?;
+        let app_id = parse_app_id(&contents)?;
+        Some(name_for_app_id(app_id, library))
+    })
+}
+
+/// The library game whose resolved exe is `exe`, or whose install folder holds it.
+fn name_from_library(exe: &str, library: &[Game]) -> Option<String> {
+    let exe = normalize(exe);
+    if exe.is_empty() {
+        return None;
+    }
+    let game = library.iter().find(|game| {
+        game.exe_path
+            .as_deref()
+            .is_some_and(|path| normalize(path) == exe)
+            || game.install_dir.as_deref().is_some_and(|dir| {
+                let dir = normalize(dir);
+                let dir = dir.trim_end_matches('\\');
+                !dir.is_empty()
+                    && exe
+                        .strip_prefix(dir)
+                        .is_some_and(|rest| rest.starts_with('\\'))
+            })
+    })?;
+    non_empty(&game.name)
+}
+
+fn parse_app_id(contents: &str) -> Option<&str> {
+    let id = contents.trim();
+    (!id.is_empty() && id.bytes().all(|byte| byte.is_ascii_digit())).then_some(id)
+}
+
+fn name_for_app_id(app_id: &str, library: &[Game]) -> String {
+    library
+        .iter()
+        .find(|game| game.source == GameSource::Steam && game.source_id.as_deref() == Some(app_id))
+        .and_then(|game| non_empty(&game.name))
+        .unwrap_or_else(|| format!("Steam App {app_id}"))
+}
+
+fn name_from_exe(exe: &str) -> Option<String> {
+    // Split on both separators so Windows paths resolve in tests off-Windows too.
+    let file_name = exe.rsplit(['\\', '/']).next()?;
+    let stem = file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(stem, _)| stem);
+    non_empty(&prettify_exe_stem(stem))
+}
+
+/// Turn an exe stem into words: split on separators, camel-case and digit
+/// boundaries, keep Roman-numeral runs and known acronyms whole, capitalize
+/// the rest (`DarkSoulsIII` -> "Dark Souls III").
+fn prettify_exe_stem(stem: &str) -> String {
+    stem.split(|character: char| matches!(character, '-' | '_' | '.') || character.is_whitespace())
+        .flat_map(split_camel)
+        .flat_map(split_acronym)
+        .map(format_word)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn split_camel(word: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = word.char_indices().collect();
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for index in 1..chars.len() {
+        let (at, current) = chars[index];
+        let previous = chars[index - 1].1;
+        let next_is_lower = chars
+            .get(index + 1)
+            .is_some_and(|(_, next)| next.is_lowercase());
+        // "HTMLParser" splits before the last capital of an upper-case run.
+        let boundary = (previous.is_lowercase() && current.is_uppercase())
+            || (previous.is_uppercase() && current.is_uppercase() && next_is_lower)
+            || (previous.is_alphabetic() && current.is_ascii_digit())
+            || (previous.is_ascii_digit() && current.is_alphabetic());
+        if boundary {
+            parts.push(&word[start..at]);
+            start = at;
+        }
+    }
+    if start < word.len() {
+        parts.push(&word[start..]);
+    }
+    parts
+}
+
+/// Split a known acronym from a trailing numeral fused into the same capital
+/// run (`GTAV`, `FFXIV`).
+fn split_acronym(word: &str) -> Vec<&str> {
+    if !is_roman(word) {
+        for acronym in ACRONYMS {
+            if let Some(rest) = word.strip_prefix(acronym) {
+                if is_roman(rest) {
+                    return vec![&word[..acronym.len()], rest];
+                }
+            }
+        }
+    }
+    vec![word]
+}
+
+fn format_word(word: &str) -> String {
+    let upper = word.to_uppercase();
+    if !word.chars().any(char::is_lowercase)
+        || ACRONYMS.contains(&upper.as_str())
+        || (word.len() > 1 && is_roman(&upper))
+    {
+        return upper;
+    }
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+/// Only I, V and X: higher numerals are rare in titles and collide with words.
+fn is_roman(word: &str) -> bool {
+    !word.is_empty()
+        && word
+            .chars()
+            .all(|character| matches!(character, 'I' | 'V' | 'X'))
+}
+
+/// Windows paths compare case-insensitively and with either separator.
+fn normalize(path: &str) -> String {
+    path.trim().replace('/', "\\").to_lowercase()
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        clean_title, detect, name_for_app_id, name_from_library, parse_app_id, prettify_exe_stem,
+        Config, Game, GameInfo, GameSource,
+    };
+
+    fn info(exe: &str, title: &str) -> GameInfo {
+        GameInfo {
+            exe: exe.to_owned(),
+            title: title.to_owned(),
+            ..GameInfo::default()
+        }
+    }
+
+    fn steam_game(app_id: &str, name: &str, install_dir: &str) -> Game {
+        Game {
+            id: format!("steam_{app_id}"),
+            name: name.to_owned(),
+            source: GameSource::Steam,
+            source_id: Some(app_id.to_owned()),
+            install_dir: Some(install_dir.to_owned()),
+            ..Game::default()
+        }
+    }
+
+    #[test]
+    fn library_install_dir_beats_a_generic_window_title() {
+        let library = [steam_game(
+            "1245620",
+            "ELDEN RING",
+            r"C:\Steam\steamapps\common\ELDEN RING\",
+        )];
+        let game = info(
+            r"c:\steam\steamapps\common\elden ring\Game\eldenring.exe",
+            "UnrealWindow",
+        );
+        assert_eq!(detect(&game, &Config::default(), &library), "ELDEN RING");
+    }
+
+    #[test]
+    fn install_dir_must_match_whole_folder_names() {
+        let library = [steam_game("1", "Portal", r"C:\Games\Portal")];
+        assert_eq!(
+            name_from_library(r"C:\Games\Portal 2\portal2.exe", &library),
+            None
+        );
+    }
+
+    #[test]
+    fn config_override_beats_library() {
+        let config: Config =
+            toml::from_str("[[games]]\nprocess = \"eldenring.exe\"\nname = \"Elden\"\n").unwrap();
+        let library = [steam_game("1245620", "ELDEN RING", r"C:\Games\ELDEN RING")];
+        let game = info(r"C:\Games\ELDEN RING\eldenring.exe", "");
+        assert_eq!(detect(&game, &config, &library), "Elden");
+    }
+
+    #[test]
+    fn falls_back_to_title_then_exe_stem() {
+        let config = Config::default();
+        assert_eq!(
+            detect(&info(r"C:\g\hk.exe", " Hollow Knight "), &config, &[]),
+            "Hollow Knight"
+        );
+        assert_eq!(detect(&info(r"C:\g\hk.exe", ""), &config, &[]), "Hk");
+        assert_eq!(detect(&info("", ""), &config, &[]), "");
+    }
+
+    #[test]
+    fn window_titles_lose_trailing_decorations() {
+        let none: &[String] = &[];
+        assert_eq!(clean_title("Elden Ring [DX12]", none), "Elden Ring");
+        assert_eq!(clean_title("Cyberpunk 2077 (GOG)", none), "Cyberpunk 2077");
+        assert_eq!(clean_title("Fortnite - Epic Games", none), "Fortnite");
+        assert_eq!(clean_title("Valheim v0.217.46 - Steam", none), "Valheim");
+        assert_eq!(
+            clean_title("Satisfactory Build 264901", none),
+            "Satisfactory"
+        );
+        assert_eq!(clean_title("Witcher 3 (DX12) [1.32]", none), "Witcher 3");
+        // Names that only look like decorations are kept.
+        assert_eq!(clean_title("Half-Life 2", none), "Half-Life 2");
+        assert_eq!(clean_title("SteamWorld Dig", none), "SteamWorld Dig");
+        assert_eq!(clean_title("Rayman Legends: Steam", none), "Rayman Legends");
+        assert_eq!(clean_title("[DX12]", none), "[DX12]");
+        assert_eq!(clean_title("Steam", none), "Steam");
+
+        let extra = ["Early Access".to_owned()];
+        assert_eq!(clean_title("Hades II | Early Access", &extra), "Hades II");
+    }
+
+    #[test]
+    fn exe_names_keep_roman_numerals_and_acronyms_whole() {
+        assert_eq!(prettify_exe_stem("DarkSoulsIII"), "Dark Souls III");
+        assert_eq!(prettify_exe_stem("GTAV"), "GTA V");
+        assert_eq!(prettify_exe_stem("horizon-zero-dawn"), "Horizon Zero Dawn");
+        assert_eq!(prettify_exe_stem("FFXIV"), "FF XIV");
+        assert_eq!(prettify_exe_stem("re4"), "RE 4");
+        assert_eq!(prettify_exe_stem("DOOMEternal"), "DOOM Eternal");
+        assert_eq!(prettify_exe_stem("Cyberpunk2077"), "Cyberpunk 2077");
+        assert_eq!(prettify_exe_stem("portal-ii"), "Portal II");
+    }
+
+    #[test]
+    fn app_ids_resolve_via_library_or_label() {
+        assert_eq!(parse_app_id("  480\r\n"), Some("480"));
+        assert_eq!(parse_app_id("abc"), None);
+        assert_eq!(parse_app_id(""), None);
+        let library = [steam_game("367520", "Hollow Knight", r"D:\hk")];
+        assert_eq!(name_for_app_id("367520", &library), "Hollow Knight");
+        assert_eq!(name_for_app_id("480", &library), "Steam App 480");
+    }
+}
@@ -8,13 +8,16 @@
 
 mod ai;
 mod commands;
+mod config;
 mod discovery;
+mod game_name;
 mod models;
 mod overlay;
 mod overlay_capture;
 mod process_watch;
 mod secrets;
 mod state;
+mod subtitles;
 
 use ai::AiState;
 use overlay::OverlayState;
@@ -38,11 +41,19 @@ fn show_main_window(app: &tauri::AppHandle) {
 
 #[allow(clippy::too_many_lines)] // Tauri builder + setup is one long, linear wiring.
 fn main() {
-    // Overlay hotkeys (Ctrl+Shift+G/T/A): modifier chords, not bare F-keys, and
-    // not Ctrl+Alt (which equals AltGr on international keyboards).
+    // Overlay hotkeys (Ctrl+Shift+G/T/A/L, X = kill switch): modifier chords, not bare F-keys, and
+    // not Ctrl+Alt (which equals AltGr on international keyboards). Keep
+    // config::BUILT_IN_HOTKEYS in step.
     let toggle = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyG);
     let translate = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyT);
     let quick_ask = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyA);
+    let cycle_language = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyL);
+    let kill = Shortcut::new(Some(Modifiers::CONTROL | Modifiers::SHIFT), Code::KeyX);
+    // Chords from `[capture] hotkey` and `[overlay] attach_hotkey` (read once;
+    // restart to rebind).
+    let chords = config::load();
+    let stage_shot = chords.capture.shortcut();
+    let toggle_attach = chords.attach_shortcut();
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
@@ -62,6 +73,14 @@ fn main() {
                         overlay::trigger(app, "translate-request");
                     } else if shortcut == &quick_ask {
                         overlay::trigger(app, "quick-ask");
+                    } else if shortcut == &cycle_language {
+                        overlay::cycle_target_language(app);
+                    } else if shortcut == &kill {
+                        overlay::kill_switch(app);
+                    } else if stage_shot.as_ref() == Some(shortcut) {
+                        overlay::stage_capture(app);
+                    } else if toggle_attach.as_ref() == Some(shortcut) {
+                        overlay::toggle_attach(app);
                     }
                 })
                 .build(),
@@ -75,13 +94,21 @@ fn main() {
                 .expect("Failed to get app data dir");
             std::fs::create_dir_all(&app_dir).expect("Failed to create app data directory");
 
+            // Read before logging starts: `[logging]` decides the log level.
+            let config = config::load();
             let log_file = std::fs::File::create(app_dir.join("launcher.log"))
                 .expect("Failed to create log file");
+            // An invalid level falls back to INFO; problems() reports it below.
             tracing_subscriber::fmt()
                 .with_writer(std::sync::Mutex::new(log_file))
                 .with_ansi(false)
+                .with_max_level(config.logging.max_level().unwrap_or(tracing::Level::INFO))
                 .init();
 
+            for problem in config.problems() {
+                tracing::warn!("config.toml: {problem}");
+            }
+
             let state_path = app_dir.join("launcher-state.json");
             let app_state = AppState::load(state_path);
 
@@ -94,13 +121,28 @@ fn main() {
                 let _ = autostart.disable();
             }
 
-            // Register the overlay hotkeys (log + continue on conflict).
-            for shortcut in [toggle, translate, quick_ask] {
+            // Register the overlay hotkeys (log + continue on conflict); an
+            // unbound configurable one is skipped.
+            for shortcut in [
+                Some(toggle),
+                Some(translate),
+                Some(quick_ask),
+                Some(cycle_language),
+                Some(kill),
+                stage_shot,
+                toggle_attach,
+            ]
+            .into_iter()
+            .flatten()
+            {
                 if let Err(e) = app.global_shortcut().register(shortcut) {
                     tracing::warn!("hotkey registration failed: {e}");
                 }
             }
 
+            // Cancel requests for, and forget, an attached game once it closes.
+            overlay::spawn_game_exit_watch(app.handle().clone());
+
             // Detect CLI provider availability off the main thread (probing the
             // claude/codex binaries can take a moment, especially via WSL).
             let detect_handle = app.handle().clone();
@@ -141,6 +183,15 @@ fn main() {
             Ok(())
         })
         .on_window_event(|window, event| {
+            if window.label() == "overlay" {
+                match event {
+                    tauri::WindowEvent::Moved(_) | tauri::WindowEvent::Resized(_) => {
+                        overlay::remember_placement(window);
+                    }
+                    tauri::WindowEvent::Focused(false) => overlay::save_placement(window),
+                    _ => {}
+                }
+            }
             if let tauri::WindowEvent::CloseRequested { api, .. } = event {
                 // The overlay window only hides; the main window drives the
                 // launcher's tray / exit behaviour.
@@ -170,13 +221,26 @@ fn main() {
             commands::games::open_game_logs,
             commands::settings::get_settings,
             commands::settings::update_settings,
+            commands::settings::get_overlay_appearance,
+            commands::settings::get_diagnostics,
+            commands::settings::create_config_file,
             commands::settings::open_url,
             commands::settings::open_config_folder,
             commands::ai::ask_sage,
             commands::ai::cancel_sage,
+            commands::ai::estimate_request,
+            commands::ai::export_conversation,
+            commands::ai::stage_screenshot,
+            commands::ai::staged_screenshot,
+            commands::ai::unstage_screenshot,
+            commands::ai::clear_staged_screenshots,
+            commands::ai::clear_translation_cache,
+            commands::ai::gemini_usage,
             commands::ai::available_providers,
             commands::ai::set_active_provider,
+            commands::ai::set_reply_length,
             commands::ai::translate_screen,
+            commands::ai::translation_languages,
             commands::ai::set_gemini_key,
             commands::ai::recheck_clis,
             overlay::capture_game,
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
@@ -27,12 +29,28 @@ pub struct Game {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(default)]
+#[allow(clippy::struct_excessive_bools)] // independent user toggles, persisted as-is
 pub struct LauncherSettings {
     pub scan_on_startup: bool,
     pub minimize_to_tray: bool,
     pub launch_on_startup: bool,
     /// Overlay AI provider selection ("gemini" / "claude" / "openai").
     pub active_provider: String,
+    /// Gemini model picked in Settings; empty uses `[api.gemini] model` (or
+    /// the built-in default).
+    pub gemini_model: String,
+    /// Overlay shows only the first paragraph of finished replies, with a
+    /// per-message "show more".
+    pub overlay_compact: bool,
+    /// Start a new overlay chat when the overlay opens over a different game.
+    pub overlay_clear_on_game_change: bool,
+    /// Enter sends an overlay question (Shift+Enter adds a line); when off,
+    /// Enter adds a line and Ctrl+Enter sends.
+    pub overlay_enter_sends: bool,
+    /// Overlay panel background opacity, 0 (see-through) to 1 (solid).
+    pub overlay_opacity: f32,
+    /// Overlay reply length ("short" / "medium" / "long").
+    pub overlay_reply_length: String,
 }
 
 impl Default for LauncherSettings {
@@ -42,7 +60,54 @@ impl Default for LauncherSettings {
             minimize_to_tray: true,
             launch_on_startup: false,
             active_provider: "gemini".to_owned(),
+            gemini_model: String::new(),
+            overlay_compact: false,
+            overlay_clear_on_game_change: false,
+            overlay_enter_sends: true,
+            overlay_opacity: 0.9,
+            overlay_reply_length: "medium".to_owned(),
+        }
+    }
+}
+
+/// Overlay window position and size in physical pixels.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct WindowPlacement {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Gemini requests sent on one local calendar day (the overlay's daily
+/// counter). A new day starts the count over.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DailyUsage {
+    pub date: Option<chrono::NaiveDate>,
+    pub requests: u32,
+}
+
+impl DailyUsage {
+    /// Requests counted on `today` (0 once the stored day has passed).
+    pub fn count_on(self, today: chrono::NaiveDate) -> u32 {
+        if self.date == Some(today) {
+            self.requests
+        } else {
+            0
+        }
+    }
+
+    /// Count one request on `today`, rolling over from an earlier day, and
+    /// return the new total.
+    pub fn record(&mut self, today: chrono::NaiveDate) -> u32 {
+        if self.date != Some(today) {
+            *self = Self {
+                date: Some(today),
+                requests: 0,
+            };
         }
+        self.requests = self.requests.saturating_add(1);
+        self.requests
     }
 }
 
@@ -50,4 +115,9 @@ impl Default for LauncherSettings {
 pub struct LauncherState {
     pub games: Vec<Game>,
     pub settings: LauncherSettings,
+    /// Last overlay placement per game, keyed by lower-case exe file name.
+    #[serde(default)]
+    pub overlay_placements: HashMap<String, WindowPlacement>,
+    #[serde(default)]
+    pub gemini_usage: DailyUsage,
 }
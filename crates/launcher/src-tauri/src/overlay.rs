@@ -5,8 +5,21 @@
 //! pure-logic tests run on Linux) the helpers degrade to no-ops so the crate
 //! still builds.
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
 use serde::Serialize;
-use tauri::{AppHandle, Emitter, Manager};
+use tauri::{
+    AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize, UserAttentionType, WebviewWindow,
+    Window,
+};
+
+use crate::config::{CompletionNotice, Config};
+use crate::models::WindowPlacement;
+use crate::state::AppState;
+
+/// How often the game-exit watchdog checks that the attached game is still open.
+const EXIT_POLL: Duration = Duration::from_secs(2);
 
 /// Snapshot of the foreground game window at the moment the overlay was opened.
 #[derive(Clone, Debug, Default, Serialize)]
@@ -16,6 +29,8 @@ pub struct GameInfo {
     pub pid: u32,
     pub exe: String,
     pub title: String,
+    /// Display name resolved at detection time (see `game_name`).
+    pub name: String,
 }
 
 /// Remembers the game window that had focus before the overlay was shown, so
@@ -23,9 +38,36 @@ pub struct GameInfo {
 #[derive(Default)]
 pub struct OverlayState {
     pub game: parking_lot::Mutex<Option<GameInfo>>,
+    /// Executable of the last game the overlay opened over, kept while it is
+    /// opened over the desktop so "New chat per game" still sees the switch.
+    last_exe: parking_lot::Mutex<Option<String>>,
+    /// The overlay was moved or resized since its placement was last saved.
+    placement_dirty: AtomicBool,
+}
+
+impl OverlayState {
+    /// Store the game the overlay just opened over (`None` over the desktop) and
+    /// report whether it differs from the last game detected.
+    fn attach(&self, game: Option<&GameInfo>) -> bool {
+        let mut last_exe = self.last_exe.lock();
+        let switched = is_other_game(last_exe.as_deref(), game);
+        if let Some(game) = game {
+            *last_exe = Some(game.exe.clone());
+        }
+        self.game.lock().clone_from(&game.cloned());
+        switched
+    }
+
+    /// Drop the attached game once its window has closed. Its exe is kept, so
+    /// opening the overlay over a different game next still starts a new chat.
+    fn forget_game(&self) -> Option<GameInfo> {
+        self.game.lock().take()
+    }
 }
 
-/// Capture the last foreground game window to a temporary PNG file.
+/// Capture the last foreground game window to a temporary PNG file and report
+/// its size: a debugging aid for black or cropped frames, run from the overlay
+/// devtools without going through a chat request.
 #[tauri::command]
 #[allow(clippy::needless_pass_by_value)] // Tauri command state is injected as an owned handle.
 pub fn capture_game(app: AppHandle) -> Result<String, String> {
@@ -38,11 +80,14 @@ pub fn capture_game(app: AppHandle) -> Result<String, String> {
         .ok_or_else(|| "no game detected -- open the overlay over a game first".to_owned())?;
 
     let png = crate::overlay_capture::capture_window_png(hwnd)?;
-    let byte_count = png.len();
+    let (width, height, byte_count) = (png.width, png.height, png.bytes.len());
     let path = std::env::temp_dir().join("sage-capture.png");
-    std::fs::write(&path, png)
+    std::fs::write(&path, png.bytes)
         .map_err(|error| format!("failed to write {}: {error}", path.display()))?;
-    Ok(format!("captured {byte_count} bytes -> {}", path.display()))
+    Ok(format!(
+        "captured {width}x{height} ({byte_count} bytes) -> {}",
+        path.display()
+    ))
 }
 
 /// Toggle the overlay window hidden <-> interactive. On hide, hand focus back to
@@ -64,6 +109,178 @@ pub fn toggle(app: &AppHandle) {
     }
 }
 
+/// Privacy kill switch (`Ctrl+Shift+X`): hide the overlay, cancel any running
+/// request, and refuse captures and requests until pressed again. Unlike the
+/// toggle hotkey it stops network activity, e.g. before sharing the screen.
+pub fn kill_switch(app: &AppHandle) {
+    let paused = app.state::<crate::ai::AiState>().toggle_paused();
+    tracing::info!(
+        "kill switch {}",
+        if paused { "engaged" } else { "released" }
+    );
+    if paused {
+        crate::subtitles::hide(app);
+        let visible = app
+            .get_webview_window("overlay")
+            .is_some_and(|overlay| overlay.is_visible().unwrap_or(false));
+        if visible {
+            toggle(app);
+        }
+    }
+    let _ = app.emit_to("overlay", "paused", paused);
+}
+
+/// Watch the game the overlay is attached to and wind down when it closes: the
+/// running request is cancelled, the overlay and subtitle bar hide, and the game
+/// is forgotten so nothing more is captured or asked about it until the overlay
+/// is opened over another one. While the overlay is shown it also follows the
+/// foreground game (see `follow_foreground_game`). Runs for the app's lifetime
+/// on its own thread.
+pub fn spawn_game_exit_watch(app: AppHandle) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(EXIT_POLL);
+        let closed = app
+            .state::<OverlayState>()
+            .game
+            .lock()
+            .as_ref()
+            .is_some_and(|game| !window_exists(game.hwnd));
+        if closed {
+            on_game_exit(&app);
+        } else {
+            follow_foreground_game(&app);
+        }
+    });
+}
+
+/// Re-attach the shown overlay when the player alt-tabs to another game or the
+/// game retitles its window (a level transition), so the name in the prompt and
+/// the capture target stay current. The overlay itself, the launcher and the
+/// desktop are not games, so focusing them keeps the attached one.
+fn follow_foreground_game(app: &AppHandle) {
+    let visible = app
+        .get_webview_window("overlay")
+        .is_some_and(|overlay| overlay.is_visible().unwrap_or(false));
+    if !visible {
+        return;
+    }
+    let Some(mut game) = foreground_game(std::process::id()) else {
+        return;
+    };
+    let unchanged = app
+        .state::<OverlayState>()
+        .game
+        .lock()
+        .as_ref()
+        .is_some_and(|current| current.hwnd == game.hwnd && current.title == game.title);
+    if unchanged {
+        return;
+    }
+    let config = crate::config::load();
+    name_game(app, &mut game, &config);
+    tracing::info!("overlay now over {} ({})", game.name, game.exe);
+    attach_game(app, Some(&game), &config);
+    let _ = app.emit_to("overlay", "game-changed", game);
+}
+
+fn on_game_exit(app: &AppHandle) {
+    let Some(game) = app.state::<OverlayState>().forget_game() else {
+        return;
+    };
+    tracing::info!("{} closed; cancelling any running request", game.exe);
+    app.state::<crate::ai::AiState>().cancel_active();
+    crate::subtitles::hide(app);
+    if let Some(overlay) = app.get_webview_window("overlay") {
+        let _ = overlay.hide();
+    }
+    let _ = app.emit_to("overlay", "game-exited", ());
+}
+
+/// Announce a reply that finished while the overlay was hidden (`[overlay]
+/// notify_on_complete`), so the player can keep playing while Sage thinks. The
+/// overlay has no taskbar button, so `Flash` flashes the launcher's (visible
+/// only when the launcher is not minimized to the tray).
+pub fn notify_reply_ready(app: &AppHandle, notice: CompletionNotice) {
+    let visible = app
+        .get_webview_window("overlay")
+        .is_some_and(|overlay| overlay.is_visible().unwrap_or(false));
+    if visible {
+        return;
+    }
+    match notice {
+        CompletionNotice::Off => {}
+        CompletionNotice::Beep => beep(),
+        CompletionNotice::Flash => {
+            if let Some(main) = app.get_webview_window("main") {
+                let _ = main.request_user_attention(Some(UserAttentionType::Informational));
+            }
+        }
+    }
+}
+
+/// Flip the overlay's attach-screenshot toggle (`[overlay] attach_hotkey`), so
+/// the next question's screenshot can be decided without reaching for the mouse.
+/// Works while the overlay is hidden; it confirms the new state in its footer.
+pub fn toggle_attach(app: &AppHandle) {
+    let _ = app.emit_to("overlay", "toggle-attach", ());
+}
+
+/// Step the translate hotkey to the next `[translation] target_languages` entry
+/// (`Ctrl+Shift+L`) and tell the overlay, which shows the new target.
+pub fn cycle_target_language(app: &AppHandle) {
+    let translation = crate::config::load().translation;
+    let targets = translation.targets();
+    let index = app
+        .state::<AppState>()
+        .cycle_translation_target(targets.len());
+    let target = targets[index];
+    tracing::info!("translation target: {target}");
+    let _ = app.emit_to("overlay", "translation-target", target);
+}
+
+/// Capture the game and stage it for the next question (`[capture] hotkey`),
+/// without opening the overlay, so a moment in fast action can be grabbed now
+/// and asked about later. Targets the game the overlay is attached to while it
+/// is open, else the foreground window.
+pub fn stage_capture(app: &AppHandle) {
+    let visible = app
+        .get_webview_window("overlay")
+        .is_some_and(|overlay| overlay.is_visible().unwrap_or(false));
+    let hwnd = if visible {
+        app.state::<OverlayState>()
+            .game
+            .lock()
+            .as_ref()
+            .map(|game| game.hwnd)
+    } else {
+        foreground_game(std::process::id()).map(|game| game.hwnd)
+    };
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let staged = match hwnd {
+            Some(hwnd) => stage_window(&app, hwnd).await,
+            None => Err("No game in the foreground to capture.".to_owned()),
+        };
+        match staged {
+            Ok(count) => {
+                tracing::info!("hotkey staged a screenshot ({count} staged)");
+                let _ = app.emit_to("overlay", "screenshot-staged", count);
+            }
+            Err(e) => {
+                tracing::warn!("hotkey capture failed: {e}");
+                let _ = app.emit_to("overlay", "screenshot-stage-failed", e);
+            }
+        }
+    });
+}
+
+async fn stage_window(app: &AppHandle, hwnd: i64) -> Result<usize, String> {
+    let ai = app.state::<crate::ai::AiState>();
+    ai.ensure_running()?;
+    let screenshot = crate::ai::capture_screenshot(hwnd).await?;
+    ai.stage(screenshot)
+}
+
 /// Show the overlay (if hidden) and fire an action event to the overlay UI, e.g.
 /// `translate-request` or `quick-ask` from a global hotkey. When already visible,
 /// keep the stored game HWND (re-detecting would find the overlay itself).
@@ -83,16 +300,158 @@ fn show_overlay(app: &AppHandle) {
     let Some(overlay) = app.get_webview_window("overlay") else {
         return;
     };
-    let game = foreground_game(std::process::id());
-    if let Some(state) = app.try_state::<OverlayState>() {
-        (*state.game.lock()).clone_from(&game);
+    let mut game = foreground_game(std::process::id());
+    let config = crate::config::load();
+    if let Some(game) = game.as_mut() {
+        name_game(app, game, &config);
     }
+    // Window title (taskbar previews, OBS window capture) follows the name.
+    let _ = overlay.set_title(&format!("{} Overlay", config.overlay.assistant_name()));
+    // Before any hotkey action event, so a quick-ask lands in the new chat.
+    attach_game(app, game.as_ref(), &config);
+    restore_placement(app, &overlay, game.as_ref());
     let _ = overlay.show();
     let _ = overlay.set_focus();
     // A null payload tells the overlay UI "no game detected".
     let _ = app.emit_to("overlay", "overlay-status", game);
 }
 
+fn name_game(app: &AppHandle, game: &mut GameInfo, config: &Config) {
+    let state = app.state::<AppState>();
+    game.name = crate::game_name::detect(game, config, &state.launcher.lock().games);
+}
+
+/// Attach the overlay to `game` and, when it is a different game than the last
+/// one and "New chat per game" is on, tell the overlay UI to start a new chat.
+fn attach_game(app: &AppHandle, game: Option<&GameInfo>, config: &Config) {
+    let switched = app
+        .try_state::<OverlayState>()
+        .is_some_and(|state| state.attach(game));
+    if !switched {
+        return;
+    }
+    let clear = config.overlay.clear_on_game_change.unwrap_or_else(|| {
+        app.state::<AppState>()
+            .launcher
+            .lock()
+            .settings
+            .overlay_clear_on_game_change
+    });
+    if clear {
+        let _ = app.emit_to("overlay", "new-chat", ());
+    }
+}
+
+/// The overlay is over a different game than the last one detected (by
+/// executable, so a title change at a level transition or a relaunch is not a
+/// switch).
+fn is_other_game(previous_exe: Option<&str>, next: Option<&GameInfo>) -> bool {
+    match (previous_exe, next) {
+        (Some(previous_exe), Some(next)) => !previous_exe.eq_ignore_ascii_case(&next.exe),
+        _ => false,
+    }
+}
+
+/// Placements are keyed by exe file name: unlike the window title it is stable
+/// across launches and menus.
+fn placement_key(game: &GameInfo) -> Option<String> {
+    let file_name = game.exe.rsplit(['\\', '/']).next()?.to_lowercase();
+    (!file_name.is_empty()).then_some(file_name)
+}
+
+/// Move/resize the overlay to where the user last left it over this game.
+/// Skipped when that spot is no longer on any connected monitor.
+fn restore_placement(app: &AppHandle, overlay: &WebviewWindow, game: Option<&GameInfo>) {
+    let Some(key) = game.and_then(placement_key) else {
+        return;
+    };
+    let Some(placement) = app
+        .state::<AppState>()
+        .launcher
+        .lock()
+        .overlay_placements
+        .get(&key)
+        .copied()
+    else {
+        return;
+    };
+    let monitors: Vec<WindowPlacement> = overlay
+        .available_monitors()
+        .unwrap_or_default()
+        .iter()
+        .map(|monitor| WindowPlacement {
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+        })
+        .collect();
+    if !is_on_screen(placement, &monitors) {
+        return;
+    }
+    let _ = overlay.set_size(PhysicalSize::new(placement.width, placement.height));
+    let _ = overlay.set_position(PhysicalPosition::new(placement.x, placement.y));
+}
+
+/// True when the placement's top-left corner lies on a monitor, so the title
+/// bar stays reachable.
+fn is_on_screen(placement: WindowPlacement, monitors: &[WindowPlacement]) -> bool {
+    placement.width > 0
+        && placement.height > 0
+        && monitors.iter().any(|monitor| {
+            let right = i64::from(monitor.x) + i64::from(monitor.width);
+            let bottom = i64::from(monitor.y) + i64::from(monitor.height);
+            placement.x >= monitor.x
+                && placement.y >= monitor.y
+                && i64::from(placement.x) < right
+                && i64::from(placement.y) < bottom
+        })
+}
+
+/// Record the overlay's placement for the current game after a move/resize.
+/// Kept in memory only; `save_placement` persists it once the drag is over.
+pub fn remember_placement(window: &Window) {
+    if !window.is_visible().unwrap_or(false) {
+        return;
+    }
+    let (Ok(position), Ok(size)) = (window.outer_position(), window.outer_size()) else {
+        return;
+    };
+    let overlay = window.state::<OverlayState>();
+    let Some(key) = overlay.game.lock().as_ref().and_then(placement_key) else {
+        return;
+    };
+    let placement = WindowPlacement {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    };
+    let state = window.state::<AppState>();
+    let previous = state
+        .launcher
+        .lock()
+        .overlay_placements
+        .insert(key, placement);
+    if previous != Some(placement) {
+        overlay.placement_dirty.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Persist a changed placement (called when the overlay loses focus, which
+/// also covers hiding it).
+pub fn save_placement(window: &Window) {
+    if window
+        .state::<OverlayState>()
+        .placement_dirty
+        .swap(false, Ordering::Relaxed)
+    {
+        if let Err(e) = window.state::<AppState>().save() {
+            tracing::error!("Failed to save overlay placement: {e}");
+        }
+    }
+}
+
 #[cfg(windows)]
 fn foreground_game(self_pid: u32) -> Option<GameInfo> {
     imp::foreground_game(self_pid)
@@ -111,17 +470,37 @@ fn focus_window(hwnd: i64) {
 #[cfg(not(windows))]
 fn focus_window(_hwnd: i64) {}
 
+#[cfg(windows)]
+fn window_exists(hwnd: i64) -> bool {
+    imp::window_exists(hwnd)
+}
+
+#[cfg(not(windows))]
+fn window_exists(_hwnd: i64) -> bool {
+    true
+}
+
+#[cfg(windows)]
+fn beep() {
+    imp::beep();
+}
+
+#[cfg(not(windows))]
+fn beep() {}
+
 #[cfg(windows)]
 mod imp {
     use super::GameInfo;
     use windows::core::PWSTR;
     use windows::Win32::Foundation::{CloseHandle, HWND};
+    use windows::Win32::System::Diagnostics::Debug::MessageBeep;
     use windows::Win32::System::Threading::{
         OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
         PROCESS_QUERY_LIMITED_INFORMATION,
     };
     use windows::Win32::UI::WindowsAndMessaging::{
-        GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId, SetForegroundWindow,
+        GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId, IsWindow,
+        SetForegroundWindow, MB_OK,
     };
 
     pub fn foreground_game(self_pid: u32) -> Option<GameInfo> {
@@ -144,6 +523,7 @@ mod imp {
                 pid,
                 exe,
                 title,
+                name: String::new(),
             })
         }
     }
@@ -168,4 +548,94 @@ mod imp {
             let _ = SetForegroundWindow(HWND(isize::try_from(hwnd).unwrap_or(0)));
         }
     }
+
+    pub fn window_exists(hwnd: i64) -> bool {
+        unsafe { IsWindow(HWND(isize::try_from(hwnd).unwrap_or(0))).as_bool() }
+    }
+
+    /// The default system sound.
+    pub fn beep() {
+        unsafe {
+            let _ = MessageBeep(MB_OK);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        is_on_screen, is_other_game, placement_key, GameInfo, OverlayState, WindowPlacement,
+    };
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> WindowPlacement {
+        WindowPlacement {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn placement_key_is_the_lowercase_exe_name() {
+        let game = GameInfo {
+            exe: r"C:\Games\ELDEN RING\eldenring.EXE".to_owned(),
+            ..GameInfo::default()
+        };
+        assert_eq!(placement_key(&game).as_deref(), Some("eldenring.exe"));
+        assert_eq!(placement_key(&GameInfo::default()), None);
+    }
+
+    #[test]
+    fn only_a_different_executable_counts_as_a_game_switch() {
+        let game = |exe: &str, title: &str| GameInfo {
+            exe: exe.to_owned(),
+            title: title.to_owned(),
+            ..GameInfo::default()
+        };
+        let elden = game(r"C:\Games\eldenring.exe", "ELDEN RING");
+        let retitled = game(r"C:\Games\EldenRing.exe", "ELDEN RING - Limgrave");
+        let witness = game(r"D:\witness\witness.exe", "The Witness");
+        assert!(!is_other_game(Some(&elden.exe), Some(&retitled)));
+        assert!(is_other_game(Some(&elden.exe), Some(&witness)));
+        assert!(!is_other_game(None, Some(&witness)));
+        assert!(!is_other_game(Some(&elden.exe), None));
+    }
+
+    #[test]
+    fn opening_over_the_desktop_keeps_the_last_game_for_switch_detection() {
+        let game = |exe: &str| GameInfo {
+            exe: exe.to_owned(),
+            ..GameInfo::default()
+        };
+        let state = OverlayState::default();
+        assert!(!state.attach(Some(&game("eldenring.exe"))));
+        assert!(!state.attach(None));
+        assert!(state.game.lock().is_none());
+        assert!(state.attach(Some(&game("witness.exe"))));
+        assert!(!state.attach(Some(&game("witness.exe"))));
+    }
+
+    #[test]
+    fn a_game_exit_still_counts_the_next_game_as_a_switch() {
+        let game = |exe: &str| GameInfo {
+            exe: exe.to_owned(),
+            ..GameInfo::default()
+        };
+        let state = OverlayState::default();
+        state.attach(Some(&game("eldenring.exe")));
+        assert!(state.forget_game().is_some());
+        assert!(state.game.lock().is_none());
+        assert!(state.attach(Some(&game("witness.exe"))));
+    }
+
+    #[test]
+    fn placements_off_every_monitor_are_rejected() {
+        let monitors = [rect(0, 0, 1920, 1080), rect(-1280, 0, 1280, 1024)];
+        assert!(is_on_screen(rect(1500, 300, 400, 760), &monitors));
+        assert!(is_on_screen(rect(-900, 10, 400, 760), &monitors));
+        assert!(!is_on_screen(rect(2000, 300, 400, 760), &monitors));
+        assert!(!is_on_screen(rect(100, 100, 0, 760), &monitors));
+        assert!(!is_on_screen(rect(100, 100, 400, 760), &[]));
+    }
 }
@@ -1,19 +1,317 @@
 //! Single-frame Windows Graphics Capture for the external overlay companion.
 
+use crate::config::CaptureRegion;
+
+/// Letterbox detection: a pixel whose brightest color channel is at most this
+/// counts as bar black (video compression and dithering rarely give pure 0).
+const LETTERBOX_THRESHOLD: u8 = 16;
+
+/// Letterbox autocrop keeps at least 1/`LETTERBOX_MIN_KEEP` of each dimension;
+/// a crop that would keep less is treated as a dark scene and skipped.
+const LETTERBOX_MIN_KEEP: u32 = 4;
+
+/// A captured frame as tightly packed 8-bit RGBA rows.
+pub struct Frame {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// An encoded PNG with the size it was encoded at.
+pub struct Png {
+    pub width: u32,
+    pub height: u32,
+    pub bytes: Vec<u8>,
+}
+
+/// PNG encoder effort (`[capture] png_compression`): smaller uploads for more
+/// CPU time per capture.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+impl PngCompression {
+    pub fn from_config(value: &str) -> Self {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "fast" => Self::Fast,
+            "" | "default" => Self::Default,
+            "best" => Self::Best,
+            other => {
+                tracing::warn!(
+                    "config.toml [capture] png_compression = {other:?} is not fast/default/best; \
+                     using default"
+                );
+                Self::Default
+            }
+        }
+    }
+}
+
+/// Capture the window, keep the configured `[capture] region` (if any), trim
+/// letterbox bars when `autocrop_letterbox` is on, scale it down to the
+/// `max_width` / `max_height` bounds, and encode it as PNG with the configured
+/// compression.
+pub fn capture_window_png(hwnd: i64) -> Result<Png, String> {
+    let mut frame = capture_window(hwnd)?;
+    let capture = crate::config::load().capture;
+    if let Some(region) = capture.region {
+        frame = crop(frame, region);
+    }
+    if capture.autocrop_letterbox {
+        if let Some(picture) = letterbox_bounds(&frame) {
+            frame = crop(frame, picture);
+        }
+    }
+    scale_and_encode(
+        frame,
+        capture.max_width,
+        capture.max_height,
+        PngCompression::from_config(&capture.png_compression),
+    )
+}
+
+/// Re-encode a captured PNG at half its width and height, for a retry after a
+/// request carrying it was rejected as too large.
+pub fn shrink_png(png: &[u8]) -> Result<Vec<u8>, String> {
+    let frame = decode_png(png)?;
+    let compression = PngCompression::from_config(&crate::config::load().capture.png_compression);
+    let (half_width, half_height) = ((frame.width / 2).max(1), (frame.height / 2).max(1));
+    scale_and_encode(frame, half_width, half_height, compression).map(|png| png.bytes)
+}
+
+/// Scale `frame` down to fit `max_width` x `max_height` (0 = unbounded) and
+/// encode it as PNG.
+fn scale_and_encode(
+    frame: Frame,
+    max_width: u32,
+    max_height: u32,
+    compression: PngCompression,
+) -> Result<Png, String> {
+    let (width, height) = fit_within(frame.width, frame.height, max_width, max_height);
+    Ok(Png {
+        width,
+        height,
+        bytes: encode_png(&resize(frame, width, height), compression)?,
+    })
+}
+
+/// Cut `region` out of `frame`, clamped to the frame's bounds. A region lying
+/// entirely outside the frame (e.g. set for a larger resolution) keeps the
+/// whole frame rather than failing the capture.
+fn crop(frame: Frame, region: CaptureRegion) -> Frame {
+    let x = region.x.min(frame.width);
+    let y = region.y.min(frame.height);
+    let width = region.width.min(frame.width - x);
+    let height = region.height.min(frame.height - y);
+    if width == 0 || height == 0 {
+        tracing::warn!(
+            "config.toml [capture] region {region:?} is outside the {}x{} frame; \
+             capturing the whole window",
+            frame.width,
+            frame.height
+        );
+        return frame;
+    }
+    if (x, y, width, height) != (region.x, region.y, region.width, region.height) {
+        tracing::warn!(
+            "config.toml [capture] region {region:?} clamped to {width}x{height} at ({x}, {y})"
+        );
+    }
+
+    let stride = frame.width as usize * 4;
+    let row_bytes = width as usize * 4;
+    let mut rgba = Vec::with_capacity(row_bytes * height as usize);
+    for row in y..y + height {
+        let start = row as usize * stride + x as usize * 4;
+        rgba.extend_from_slice(&frame.rgba[start..start + row_bytes]);
+    }
+    Frame {
+        width,
+        height,
+        rgba,
+    }
+}
+
+/// The picture inside `frame`'s uniform black borders, or `None` when there are
+/// no bars, the frame is entirely dark, or trimming would keep less than
+/// 1/`LETTERBOX_MIN_KEEP` of either dimension (a dark scene, not bars).
+fn letterbox_bounds(frame: &Frame) -> Option<CaptureRegion> {
+    let (width, height) = (frame.width, frame.height);
+    let stride = frame.width as usize * 4;
+    let dark = |x: u32, y: u32| {
+        let pixel = &frame.rgba[y as usize * stride + x as usize * 4..][..3];
+        pixel.iter().all(|&channel| channel <= LETTERBOX_THRESHOLD)
+    };
+    let dark_row = |y: u32| (0..width).all(|x| dark(x, y));
+    let dark_col = |x: u32| (0..height).all(|y| dark(x, y));
+    // Length of the run of dark lines from the start of `lines`.
+    let run = |lines: &mut dyn Iterator<Item = u32>, is_dark: &dyn Fn(u32) -> bool| {
+        let mut count = 0;
+        for line in lines {
+            if !is_dark(line) {
+                break;
+            }
+            count += 1;
+        }
+        count
+    };
+
+    let top = run(&mut (0..height), &dark_row);
+    if top == height {
+        return None;
+    }
+    let bottom = run(&mut (top..height).rev(), &dark_row);
+    let left = run(&mut (0..width), &dark_col);
+    let right = run(&mut (left..width).rev(), &dark_col);
+    if top + bottom + left + right == 0 {
+        return None;
+    }
+    let picture = CaptureRegion {
+        x: left,
+        y: top,
+        width: width - left - right,
+        height: height - top - bottom,
+    };
+    if picture.width * LETTERBOX_MIN_KEEP < width || picture.height * LETTERBOX_MIN_KEEP < height {
+        return None;
+    }
+    Some(picture)
+}
+
+/// The largest size within `max_width` x `max_height` (0 = unbounded) with the
+/// frame's aspect ratio, scaled by whichever bound is more restrictive. Never
+/// upscales.
+fn fit_within(width: u32, height: u32, max_width: u32, max_height: u32) -> (u32, u32) {
+    let bound_w = if max_width == 0 {
+        width
+    } else {
+        max_width.min(width)
+    };
+    let bound_h = if max_height == 0 {
+        height
+    } else {
+        max_height.min(height)
+    };
+    if (bound_w, bound_h) == (width, height) {
+        return (width, height);
+    }
+    // bound_w / width <= bound_h / height, compared without rounding.
+    if u64::from(bound_w) * u64::from(height) <= u64::from(bound_h) * u64::from(width) {
+        (bound_w, scale(height, bound_w, width))
+    } else {
+        (scale(width, bound_h, height), bound_h)
+    }
+}
+
+/// `value * num / den`, rounded, at least 1. `num <= den`, so it fits in u32.
+fn scale(value: u32, num: u32, den: u32) -> u32 {
+    let scaled = (u64::from(value) * u64::from(num) + u64::from(den) / 2) / u64::from(den);
+    u32::try_from(scaled).unwrap_or(value).max(1)
+}
+
+/// Downscale `frame` to `width` x `height` by averaging the source pixels each
+/// output pixel covers (a box filter: cheap, and keeps small text legible
+/// better than nearest-neighbour).
+fn resize(frame: Frame, width: u32, height: u32) -> Frame {
+    if (width, height) == (frame.width, frame.height) {
+        return frame;
+    }
+    // Source span [start, end) covered by output index `i` of `dst` along an
+    // axis of `src` pixels; never empty.
+    let span = |i: u32, dst: u32, src: u32| {
+        let start = i as usize * src as usize / dst as usize;
+        let end = ((i as usize + 1) * src as usize / dst as usize).max(start + 1);
+        start..end
+    };
+    let stride = frame.width as usize * 4;
+    let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+    for out_y in 0..height {
+        let rows = span(out_y, height, frame.height);
+        for out_x in 0..width {
+            let cols = span(out_x, width, frame.width);
+            let mut sum = [0u64; 4];
+            for row in rows.clone() {
+                for col in cols.clone() {
+                    let pixel = &frame.rgba[row * stride + col * 4..][..4];
+                    for (total, &channel) in sum.iter_mut().zip(pixel) {
+                        *total += u64::from(channel);
+                    }
+                }
+            }
+            let count = (rows.len() * cols.len()) as u64;
+            rgba.extend(
+                sum.map(|total| u8::try_from((total + count / 2) / count).unwrap_or(u8::MAX)),
+            );
+        }
+    }
+    Frame {
+        width,
+        height,
+        rgba,
+    }
+}
+
 #[cfg(windows)]
-pub fn capture_window_png(hwnd: i64) -> Result<Vec<u8>, String> {
-    imp::capture_window_png(hwnd)
+fn capture_window(hwnd: i64) -> Result<Frame, String> {
+    imp::capture_window(hwnd)
 }
 
 #[cfg(not(windows))]
-pub fn capture_window_png(_hwnd: i64) -> Result<Vec<u8>, String> {
+fn capture_window(_hwnd: i64) -> Result<Frame, String> {
     Err("screen capture is only supported on Windows".into())
 }
 
+fn encode_png(frame: &Frame, compression: PngCompression) -> Result<Vec<u8>, String> {
+    let mut output = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut output, frame.width, frame.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        match compression {
+            PngCompression::Fast => encoder.set_compression(png::Compression::Fast),
+            PngCompression::Default => encoder.set_compression(png::Compression::Default),
+            PngCompression::Best => encoder.set_compression(png::Compression::Best),
+        }
+        let mut writer = encoder
+            .write_header()
+            .map_err(|error| format!("failed to write PNG header: {error}"))?;
+        writer
+            .write_image_data(&frame.rgba)
+            .map_err(|error| format!("failed to encode PNG: {error}"))?;
+    }
+    Ok(output)
+}
+
+/// Decode one of our own captures (8-bit RGBA PNG) back into a frame.
+fn decode_png(png: &[u8]) -> Result<Frame, String> {
+    let mut reader = png::Decoder::new(png)
+        .read_info()
+        .map_err(|error| format!("failed to read PNG: {error}"))?;
+    let mut rgba = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut rgba)
+        .map_err(|error| format!("failed to decode PNG: {error}"))?;
+    if (info.color_type, info.bit_depth) != (png::ColorType::Rgba, png::BitDepth::Eight) {
+        return Err("unsupported PNG format (expected 8-bit RGBA)".to_owned());
+    }
+    rgba.truncate(info.buffer_size());
+    Ok(Frame {
+        width: info.width,
+        height: info.height,
+        rgba,
+    })
+}
+
 #[cfg(windows)]
 mod imp {
     use std::time::{Duration, Instant};
 
+    use super::Frame;
+
     use windows::core::{factory, Interface};
     use windows::Graphics::Capture::{
         Direct3D11CaptureFrame, Direct3D11CaptureFramePool, GraphicsCaptureItem,
@@ -36,7 +334,7 @@ mod imp {
     const FRAME_TIMEOUT: Duration = Duration::from_secs(2);
     const FRAME_POLL_INTERVAL: Duration = Duration::from_millis(16);
 
-    pub fn capture_window_png(hwnd: i64) -> Result<Vec<u8>, String> {
+    pub fn capture_window(hwnd: i64) -> Result<Frame, String> {
         let (d3d_device, d3d_context, capture_device) = create_device()?;
         let item = create_capture_item(hwnd)?;
         let size = item
@@ -114,9 +412,9 @@ mod imp {
         pool: &Direct3D11CaptureFramePool,
         device: &ID3D11Device,
         context: &ID3D11DeviceContext,
-    ) -> Result<Vec<u8>, String> {
+    ) -> Result<Frame, String> {
         let frame = wait_for_frame(pool)?;
-        let result = read_frame_png(&frame, device, context);
+        let result = read_frame(&frame, device, context);
         let _ = frame.Close();
         result
     }
@@ -134,11 +432,11 @@ mod imp {
         }
     }
 
-    fn read_frame_png(
+    fn read_frame(
         frame: &Direct3D11CaptureFrame,
         device: &ID3D11Device,
         context: &ID3D11DeviceContext,
-    ) -> Result<Vec<u8>, String> {
+    ) -> Result<Frame, String> {
         let surface = frame
             .Surface()
             .map_err(|error| format!("failed to get capture surface: {error}"))?;
@@ -178,7 +476,11 @@ mod imp {
         }
         let pixels = read_mapped_rgba(&mapped, desc.Width, desc.Height);
         unsafe { context.Unmap(&staging, 0) };
-        encode_png(desc.Width, desc.Height, &pixels?)
+        Ok(Frame {
+            width: desc.Width,
+            height: desc.Height,
+            rgba: pixels?,
+        })
     }
 
     fn read_mapped_rgba(
@@ -221,20 +523,188 @@ mod imp {
         }
         Ok(rgba)
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        crop, decode_png, encode_png, fit_within, letterbox_bounds, resize, scale_and_encode,
+        CaptureRegion, Frame, PngCompression,
+    };
+
+    /// A frame whose every pixel encodes its own (x, y) in the red/green bytes.
+    #[allow(clippy::cast_possible_truncation)] // test frames are under 256 px
+    fn numbered_frame(width: u32, height: u32) -> Frame {
+        let mut rgba = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                rgba.extend_from_slice(&[x as u8, y as u8, 0, 255]);
+            }
+        }
+        Frame {
+            width,
+            height,
+            rgba,
+        }
+    }
+
+    fn region(x: u32, y: u32, width: u32, height: u32) -> CaptureRegion {
+        CaptureRegion {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn crop_keeps_only_the_region() {
+        let cropped = crop(numbered_frame(8, 6), region(2, 3, 4, 2));
+        assert_eq!((cropped.width, cropped.height), (4, 2));
+        assert_eq!(&cropped.rgba[..4], &[2, 3, 0, 255]);
+        assert_eq!(&cropped.rgba[cropped.rgba.len() - 4..], &[5, 4, 0, 255]);
+    }
+
+    #[test]
+    fn crop_clamps_to_the_frame() {
+        let cropped = crop(numbered_frame(8, 6), region(6, 4, 100, 100));
+        assert_eq!((cropped.width, cropped.height), (2, 2));
+        assert_eq!(&cropped.rgba[..4], &[6, 4, 0, 255]);
+
+        let whole = crop(numbered_frame(8, 6), region(20, 0, 4, 4));
+        assert_eq!((whole.width, whole.height), (8, 6));
+    }
+
+    /// A mid-gray `width` x `height` picture surrounded by near-black bars of
+    /// the given `[left, top, right, bottom]` thickness.
+    fn letterboxed(width: u32, height: u32, bars: [u32; 4]) -> Frame {
+        let [left, top, right, bottom] = bars;
+        let (full_w, full_h) = (left + width + right, top + height + bottom);
+        let mut rgba = Vec::new();
+        for y in 0..full_h {
+            for x in 0..full_w {
+                let inside = (left..left + width).contains(&x) && (top..top + height).contains(&y);
+                rgba.extend_from_slice(if inside {
+                    &[128, 128, 128, 255]
+                } else {
+                    &[6, 3, 9, 255]
+                });
+            }
+        }
+        Frame {
+            width: full_w,
+            height: full_h,
+            rgba,
+        }
+    }
+
+    #[test]
+    fn letterbox_trims_known_bars() {
+        let frame = letterboxed(40, 20, [0, 5, 0, 7]);
+        assert_eq!(letterbox_bounds(&frame), Some(region(0, 5, 40, 20)));
+
+        let frame = letterboxed(30, 24, [6, 2, 4, 3]);
+        let picture = letterbox_bounds(&frame).unwrap();
+        assert_eq!(picture, region(6, 2, 30, 24));
+        let cropped = crop(frame, picture);
+        assert!(cropped.rgba.chunks(4).all(|p| p == [128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn letterbox_leaves_unbarred_frames_alone() {
+        assert_eq!(letterbox_bounds(&letterboxed(16, 9, [0; 4])), None);
+        // A dark scene: a small lit patch is not a picture between bars.
+        assert_eq!(letterbox_bounds(&letterboxed(4, 4, [20, 20, 20, 20])), None);
+        // All black (a loading screen).
+        assert_eq!(letterbox_bounds(&letterboxed(0, 0, [16, 9, 0, 0])), None);
+    }
+
+    #[test]
+    fn fit_scales_landscape_by_the_tighter_bound() {
+        // Super-ultrawide: the width bound binds.
+        assert_eq!(fit_within(5120, 1440, 1920, 1080), (1920, 540));
+        // Width-only limit keeps working on its own.
+        assert_eq!(fit_within(3840, 2160, 1920, 0), (1920, 1080));
+    }
+
+    #[test]
+    fn fit_scales_portrait_by_height() {
+        assert_eq!(fit_within(1080, 2400, 1920, 1080), (486, 1080));
+        assert_eq!(fit_within(1080, 2400, 0, 1200), (540, 1200));
+    }
+
+    #[test]
+    fn fit_leaves_small_or_unbounded_frames_alone() {
+        assert_eq!(fit_within(1280, 720, 1920, 1080), (1280, 720));
+        assert_eq!(fit_within(3840, 2160, 0, 0), (3840, 2160));
+        assert_eq!(fit_within(4000, 1, 100, 0), (100, 1));
+    }
+
+    #[test]
+    fn resize_averages_covered_pixels() {
+        let resized = resize(numbered_frame(4, 4), 2, 2);
+        assert_eq!((resized.width, resized.height), (2, 2));
+        assert_eq!(resized.rgba.len(), 2 * 2 * 4);
+        // Top-left covers x/y 0..2: mean 0.5, rounded up.
+        assert_eq!(&resized.rgba[..4], &[1, 1, 0, 255]);
+        // Bottom-right covers x/y 2..4: mean 2.5.
+        assert_eq!(&resized.rgba[12..], &[3, 3, 0, 255]);
+
+        let same = resize(numbered_frame(3, 2), 3, 2);
+        assert_eq!(same.rgba, numbered_frame(3, 2).rgba);
+    }
+
+    #[test]
+    fn png_round_trips_and_scales_for_a_retry() {
+        let png = encode_png(&numbered_frame(6, 4), PngCompression::Fast).unwrap();
+        let decoded = decode_png(&png).unwrap();
+        assert_eq!((decoded.width, decoded.height), (6, 4));
+        assert_eq!(decoded.rgba, numbered_frame(6, 4).rgba);
+
+        let half = scale_and_encode(decoded, 3, 2, PngCompression::Fast).unwrap();
+        assert_eq!((half.width, half.height), (3, 2));
+        let reread = decode_png(&half.bytes).unwrap();
+        assert_eq!((reread.width, reread.height), (3, 2));
+    }
+
+    #[test]
+    #[allow(clippy::cast_possible_truncation)] // synthetic pixel values wrap by design
+    fn compression_levels_trade_size_for_effort() {
+        // Smooth gradients with a little per-pixel noise, standing in for game
+        // scenery (kept small so the debug-build test stays fast).
+        let (width, height) = (480_u32, 270_u32);
+        let mut rgba = Vec::new();
+        let mut seed = 0x2545_f491_u32;
+        for y in 0..height {
+            for x in 0..width {
+                seed ^= seed << 13;
+                seed ^= seed >> 17;
+                seed ^= seed << 5;
+                let noise = (seed & 0x7) as u8;
+                rgba.extend_from_slice(&[
+                    (x * 255 / width) as u8 ^ noise,
+                    (y * 255 / height) as u8 ^ noise,
+                    (x + y) as u8,
+                    255,
+                ]);
+            }
+        }
+        let frame = Frame {
+            width,
+            height,
+            rgba,
+        };
+        let fast = encode_png(&frame, PngCompression::Fast).unwrap().len();
+        let default = encode_png(&frame, PngCompression::Default).unwrap().len();
+        let best = encode_png(&frame, PngCompression::Best).unwrap().len();
+        assert!(best <= default && default < fast);
+    }
 
-    fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Result<Vec<u8>, String> {
-        let mut output = Vec::new();
-        {
-            let mut encoder = png::Encoder::new(&mut output, width, height);
-            encoder.set_color(png::ColorType::Rgba);
-            encoder.set_depth(png::BitDepth::Eight);
-            let mut writer = encoder
-                .write_header()
-                .map_err(|error| format!("failed to write PNG header: {error}"))?;
-            writer
-                .write_image_data(rgba)
-                .map_err(|error| format!("failed to encode PNG: {error}"))?;
-        }
-        Ok(output)
+    #[test]
+    fn unknown_compression_names_fall_back_to_default() {
+        assert_eq!(PngCompression::from_config("FAST"), PngCompression::Fast);
+        assert_eq!(PngCompression::from_config("best"), PngCompression::Best);
+        assert_eq!(PngCompression::from_config(""), PngCompression::Default);
+        assert_eq!(PngCompression::from_config("max"), PngCompression::Default);
     }
 }
@@ -1,5 +1,6 @@
 use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 use parking_lot::Mutex;
 
@@ -11,6 +12,9 @@ pub struct AppState {
     /// Game ids with an active play session (a running process being watched).
     /// Guards against launching the same game twice.
     pub active_sessions: Mutex<HashSet<String>>,
+    /// Position in `[translation] target_languages` picked by the cycle hotkey
+    /// (this session only).
+    translation_target: AtomicUsize,
     /// Serializes `save()` so the watcher thread and command threads cannot
     /// interleave writes to the shared temp file.
     save_lock: Mutex<()>,
@@ -48,6 +52,7 @@ impl AppState {
             launcher: Mutex::new(launcher),
             state_path,
             active_sessions: Mutex::new(HashSet::new()),
+            translation_target: AtomicUsize::new(0),
             save_lock: Mutex::new(()),
         }
     }
@@ -63,6 +68,41 @@ impl AppState {
         std::fs::write(&tmp_path, json).map_err(|e| e.to_string())?;
         std::fs::rename(&tmp_path, &self.state_path).map_err(|e| e.to_string())
     }
+
+    /// The Gemini model picked in Settings; empty defers to config.toml.
+    pub fn gemini_model(&self) -> String {
+        self.launcher.lock().settings.gemini_model.clone()
+    }
+
+    /// The cycle hotkey's position in the translation target list.
+    pub fn translation_target(&self) -> usize {
+        self.translation_target.load(Ordering::Relaxed)
+    }
+
+    /// Step to the next of `count` translation targets, wrapping, and return
+    /// the new position.
+    pub fn cycle_translation_target(&self, count: usize) -> usize {
+        let next = (self.translation_target() + 1) % count.max(1);
+        self.translation_target.store(next, Ordering::Relaxed);
+        next
+    }
+
+    /// Count one successful Gemini request against today's (local) total and
+    /// persist it. Returns the new total.
+    pub fn record_gemini_request(&self) -> u32 {
+        let today = chrono::Local::now().date_naive();
+        let count = self.launcher.lock().gemini_usage.record(today);
+        if let Err(e) = self.save() {
+            tracing::warn!("failed to save the daily request count: {e}");
+        }
+        count
+    }
+
+    /// Gemini requests sent so far today (local time).
+    pub fn gemini_requests_today(&self) -> u32 {
+        let today = chrono::Local::now().date_naive();
+        self.launcher.lock().gemini_usage.count_on(today)
+    }
 }
 
 #[cfg(test)]
@@ -205,4 +245,38 @@ mod tests {
         assert!(serde_json::from_str::<serde_json::Value>(&contents).is_ok());
         cleanup(&path);
     }
+
+    #[test]
+    fn daily_usage_rolls_over_at_local_midnight() {
+        use crate::models::DailyUsage;
+        let at = |stamp: &str| {
+            chrono::NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M:%S")
+                .unwrap()
+                .date()
+        };
+        let before = at("2026-03-01 23:59:59");
+        let after = at("2026-03-02 00:00:00");
+
+        let mut usage = DailyUsage::default();
+        assert_eq!(usage.count_on(before), 0);
+        assert_eq!(usage.record(before), 1);
+        assert_eq!(usage.record(before), 2);
+        assert_eq!(usage.count_on(before), 2);
+        assert_eq!(usage.count_on(after), 0, "a new day starts at zero");
+        assert_eq!(usage.record(after), 1);
+        assert_eq!(usage.count_on(before), 0);
+    }
+
+    #[test]
+    fn gemini_usage_persists_across_reloads() {
+        let path = temp_state_path("usage");
+        let app = AppState::load(path.clone());
+        assert_eq!(app.gemini_requests_today(), 0);
+        app.record_gemini_request();
+        assert_eq!(app.record_gemini_request(), 2);
+
+        let reloaded = AppState::load(path.clone());
+        assert_eq!(reloaded.gemini_requests_today(), 2);
+        cleanup(&path);
+    }
 }
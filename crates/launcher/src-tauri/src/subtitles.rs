@@ -0,0 +1,101 @@
+//! Optional subtitle bar for screen translation (`[translation] subtitle_bar`):
+//! a thin, click-through, always-on-top window along the bottom of the game's
+//! monitor that shows the latest translation apart from the chat panel, and
+//! hides itself a few seconds later (`subtitle_secs`).
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager, PhysicalPosition, PhysicalSize};
+
+use crate::models::WindowPlacement;
+
+/// Payload of the `subtitle` event sent to the subtitle window.
+#[derive(Clone, Serialize)]
+struct Subtitle<'a> {
+    text: &'a str,
+    /// Seconds before the bar hides itself; 0 keeps it until the next one.
+    secs: u64,
+}
+
+/// Show `text` in the subtitle bar, placed on the monitor the overlay is on.
+pub fn show(app: &AppHandle, text: &str, secs: u64) {
+    let Some(bar) = app.get_webview_window("subtitles") else {
+        return;
+    };
+    let monitor = app
+        .get_webview_window("overlay")
+        .and_then(|overlay| overlay.current_monitor().ok().flatten())
+        .or_else(|| app.primary_monitor().ok().flatten());
+    if let Some(monitor) = monitor {
+        let placement = bar_placement(WindowPlacement {
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+        });
+        let _ = bar.set_size(PhysicalSize::new(placement.width, placement.height));
+        let _ = bar.set_position(PhysicalPosition::new(placement.x, placement.y));
+    }
+    // Never takes clicks: the game underneath stays fully playable.
+    let _ = bar.set_ignore_cursor_events(true);
+    let _ = bar.show();
+    let _ = app.emit_to("subtitles", "subtitle", Subtitle { text, secs });
+}
+
+/// Hide the subtitle bar (the kill switch hides everything Sage shows).
+pub fn hide(app: &AppHandle) {
+    if let Some(bar) = app.get_webview_window("subtitles") {
+        let _ = bar.hide();
+    }
+}
+
+/// The bar's rectangle on `monitor`: 60% of its width, centred, a tenth of its
+/// height tall, raised a little off the bottom edge where games draw their own
+/// HUD.
+fn bar_placement(monitor: WindowPlacement) -> WindowPlacement {
+    let width = monitor.width / 5 * 3;
+    let height = monitor.height / 10;
+    let lift = monitor.height / 12;
+    WindowPlacement {
+        x: monitor
+            .x
+            .saturating_add_unsigned((monitor.width - width) / 2),
+        y: monitor
+            .y
+            .saturating_add_unsigned(monitor.height - height - lift),
+        width,
+        height,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bar_placement, WindowPlacement};
+
+    #[test]
+    fn bar_sits_centred_near_the_bottom_of_the_monitor() {
+        let bar = bar_placement(WindowPlacement {
+            x: 0,
+            y: 0,
+            width: 1920,
+            height: 1080,
+        });
+        assert_eq!(
+            bar,
+            WindowPlacement {
+                x: 384,
+                y: 882,
+                width: 1152,
+                height: 108,
+            }
+        );
+
+        // A secondary monitor to the left of the primary one.
+        let bar = bar_placement(WindowPlacement {
+            x: -2560,
+            y: 0,
+            width: 2560,
+            height: 1440,
+        });
+        assert_eq!((bar.x, bar.y), (-2048, 1176));
+    }
+}
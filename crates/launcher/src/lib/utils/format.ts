@@ -27,3 +27,17 @@ export function formatLastPlayed(dateStr: string | null): string {
     return 'Unknown';
   }
 }
+
+/** Compact token count for the overlay footer, e.g. `840` or `12.4k`. */
+export function formatTokens(tokens: number): string {
+  if (tokens < 1000) return `${tokens}`;
+  return `${(tokens / 1000).toFixed(tokens < 10000 ? 1 : 0)}k`;
+}
+
+/** Request latency for a reply's meta line, e.g. `2.4s · 380ms to first word`. */
+export function formatLatency(totalMs: number, firstChunkMs: number | null): string {
+  const ms = (value: number) => (value < 1000 ? `${value}ms` : `${(value / 1000).toFixed(1)}s`);
+  return firstChunkMs === null
+    ? ms(totalMs)
+    : `${ms(totalMs)} · ${ms(firstChunkMs)} to first word`;
+}